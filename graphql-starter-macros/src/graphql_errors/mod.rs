@@ -0,0 +1,157 @@
+use darling::{ast, FromDeriveInput, FromField, FromMeta, FromVariant};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{DeriveInput, Generics, Ident, Path, Type, Visibility};
+
+#[derive(FromDeriveInput)]
+#[darling(attributes(graphql_errors), supports(enum_unit, enum_named))]
+struct ErrorsInput {
+    ident: Ident,
+    vis: Visibility,
+    generics: Generics,
+    data: ast::Data<ErrorVariant, ()>,
+    #[darling(rename = "crate")]
+    crate_path: Option<Path>,
+    #[darling(multiple, rename = "result")]
+    results: Vec<ResultInput>,
+}
+
+#[derive(FromVariant)]
+#[darling(attributes(graphql_errors))]
+struct ErrorVariant {
+    ident: Ident,
+    fields: ast::Fields<ErrorField>,
+}
+
+#[derive(FromField)]
+struct ErrorField {
+    ident: Option<Ident>,
+    ty: Type,
+}
+
+#[derive(FromMeta)]
+struct ResultInput {
+    name: Ident,
+    payload: Path,
+}
+
+pub(crate) fn r#impl(input: DeriveInput) -> TokenStream {
+    let input = match ErrorsInput::from_derive_input(&input) {
+        Ok(input) => input,
+        Err(err) => return err.write_errors(),
+    };
+    // Every instantiation of a generic enum would register the same GraphQL types
+    if !input.generics.params.is_empty() {
+        return darling::Error::custom("GraphQLErrors can't be derived for generic enums")
+            .with_span(&input.generics)
+            .write_errors();
+    }
+    let crate_expr = match &input.crate_path {
+        Some(path) => quote!(#path),
+        None => quote!(graphql_starter),
+    };
+    let async_graphql = quote!(#crate_expr::crates::async_graphql);
+    let async_graphql_str = async_graphql.to_string();
+    let error_info = quote!(#crate_expr::crates::error_info);
+    let err_ty = quote!(::std::boxed::Box<#crate_expr::error::Error>);
+    let enum_ident = &input.ident;
+    let vis = &input.vis;
+    let variants = input.data.take_enum().expect("enum");
+
+    // Generate an object for each variant
+    let mut objects = Vec::with_capacity(variants.len());
+    let mut object_idents = Vec::with_capacity(variants.len());
+    let mut conversions = Vec::with_capacity(variants.len());
+    for variant in &variants {
+        let variant_ident = &variant.ident;
+        let object_ident = if variant_ident.to_string().ends_with("Error") {
+            variant_ident.clone()
+        } else {
+            format_ident!("{variant_ident}Error")
+        };
+        let field_idents = variant
+            .fields
+            .iter()
+            .map(|f| f.ident.as_ref().expect("named fields"))
+            .collect::<Vec<_>>();
+        let field_types = variant.fields.iter().map(|f| &f.ty);
+        let doc = format!("Object for the [{enum_ident}::{variant_ident}] error");
+
+        objects.push(quote!(
+            #[doc = #doc]
+            #[derive(Debug, Clone, #async_graphql::SimpleObject)]
+            #[graphql(crate = #async_graphql_str)]
+            #vis struct #object_ident {
+                /// The error message
+                pub message: String,
+                #( pub #field_idents: #field_types, )*
+            }
+        ));
+        let pattern = if field_idents.is_empty() {
+            quote!(#enum_ident::#variant_ident)
+        } else {
+            quote!(#enum_ident::#variant_ident { #( #field_idents ),* })
+        };
+        conversions.push((pattern, object_ident.clone(), field_idents));
+        object_idents.push(object_ident);
+    }
+
+    // Generate the union for each result
+    let results = input.results.iter().map(|result| {
+        let result_ident = &result.name;
+        let payload = &result.payload;
+        let doc = format!("Result of the operation, either the payload or one of the [{enum_ident}] errors");
+        let from_result_doc = format!(
+            "Maps the result of the operation, returning the [{enum_ident}] errors as data and propagating any other \
+             error"
+        );
+        let arms = conversions.iter().map(|(pattern, object_ident, field_idents)| {
+            quote!(
+                #pattern => Self::#object_ident(#object_ident {
+                    message,
+                    #( #field_idents: ::std::clone::Clone::clone(#field_idents), )*
+                })
+            )
+        });
+        quote!(
+            #[doc = #doc]
+            #[derive(#async_graphql::Union)]
+            #[graphql(crate = #async_graphql_str)]
+            #vis enum #result_ident {
+                Ok(#payload),
+                #( #object_idents(#object_idents), )*
+            }
+
+            impl ::std::convert::TryFrom<#err_ty> for #result_ident {
+                type Error = #err_ty;
+
+                fn try_from(err: #err_ty) -> ::std::result::Result<Self, Self::Error> {
+                    let ::std::option::Option::Some(info) = err.downcast_info::<#enum_ident>() else {
+                        return ::std::result::Result::Err(err);
+                    };
+                    let message = #error_info::ErrorInfo::message(info);
+                    ::std::result::Result::Ok(match info {
+                        #( #arms, )*
+                    })
+                }
+            }
+
+            impl #result_ident {
+                #[doc = #from_result_doc]
+                pub fn from_result(
+                    res: ::std::result::Result<#payload, #err_ty>,
+                ) -> ::std::result::Result<Self, #err_ty> {
+                    match res {
+                        ::std::result::Result::Ok(payload) => ::std::result::Result::Ok(Self::Ok(payload)),
+                        ::std::result::Result::Err(err) => ::std::convert::TryFrom::try_from(err),
+                    }
+                }
+            }
+        )
+    });
+
+    quote!(
+        #( #objects )*
+        #( #results )*
+    )
+}
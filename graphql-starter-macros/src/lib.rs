@@ -1,5 +1,7 @@
 #![forbid(unsafe_code)]
 
+#[cfg(feature = "graphql")]
+mod graphql_errors;
 #[cfg(feature = "sqlx")]
 mod sqlx;
 #[cfg(feature = "subject")]
@@ -34,10 +36,59 @@ pub fn sqlx_expand_paginated_query(input: TokenStream) -> TokenStream {
     sqlx::pagination::r#impl(input).into()
 }
 
+#[cfg(feature = "graphql")]
+/// Generates typed GraphQL errors from an `ErrorInfo` enum, so they can be returned as data.
+///
+/// An object is generated for each variant, named after it with an `Error` suffix (if missing), including the
+/// `message` and the variant fields.
+///
+/// For every `result(name = Ident, payload = Path)` attribute, a union of the payload and every error object is
+/// generated, along with a conversion from `Box<Error>` that fails with the same error if it's not one of the enum.
+///
+/// Generic enums are not supported, as every instantiation would register the same GraphQL types. The path to the
+/// `graphql_starter` crate can be overridden with `crate = path`, when it's renamed or re-exported.
+///
+/// ``` rust ignore
+/// #[derive(Clone, ErrorInfo, GraphQLErrors)]
+/// #[graphql_errors(result(name = CreateUserResult, payload = User))]
+/// pub enum UserErrorCode {
+///     #[error(status = StatusCode::CONFLICT, message = "The username \"{username}\" is already taken")]
+///     UserUsernameTaken { username: String },
+/// }
+///
+/// async fn create_user(&self, ctx: &Context<'_>, input: CreateUserInput) -> GraphQLResult<CreateUserResult> {
+///     Ok(CreateUserResult::from_result(service.create_user(input).await)?)
+/// }
+/// ```
+#[proc_macro_error]
+#[proc_macro_derive(GraphQLErrors, attributes(graphql_errors))]
+pub fn graphql_errors(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+    graphql_errors::r#impl(input).into()
+}
+
 #[cfg(feature = "subject")]
 /// Derives the `Subject` trait.
+///
+/// Fields can be annotated to implement the optional accessor traits as well:
+/// - `#[subject(id)]`: implements `SubjectId` and `Display` (unless `#[subject(skip_display)]` is set on the type)
+/// - `#[subject(tenant)]`: implements `SubjectTenant`, the field can be an `Option`
+/// - `#[subject(scopes)]`: implements `SubjectScopes`, the field must be a collection of strings (or an `Option` of it)
+/// - `#[subject(roles)]`: implements `SubjectRoles`, the field must be a collection of strings (or an `Option` of it)
+///
+/// ``` rust ignore
+/// #[derive(Debug, Clone, Subject)]
+/// pub struct User {
+///     #[subject(id)]
+///     id: Uuid,
+///     #[subject(tenant)]
+///     organization: Option<String>,
+///     #[subject(scopes)]
+///     scopes: Vec<String>,
+/// }
+/// ```
 #[proc_macro_error]
-#[proc_macro_derive(Subject)]
+#[proc_macro_derive(Subject, attributes(subject))]
 pub fn subject(input: TokenStream) -> TokenStream {
     let input = syn::parse_macro_input!(input as syn::DeriveInput);
     subject::r#impl(input).into()
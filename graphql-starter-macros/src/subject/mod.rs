@@ -1,11 +1,137 @@
+use darling::{ast, util::Flag, FromDeriveInput, FromField};
 use proc_macro2::TokenStream;
-use quote::quote;
-use syn::DeriveInput;
+use quote::{quote, ToTokens};
+use syn::{DeriveInput, Ident, Index, Type};
+
+#[derive(FromDeriveInput)]
+#[darling(attributes(subject))]
+struct SubjectInput {
+    ident: Ident,
+    generics: syn::Generics,
+    data: ast::Data<(), SubjectField>,
+    skip_display: Flag,
+}
+
+#[derive(FromField)]
+#[darling(attributes(subject))]
+struct SubjectField {
+    ident: Option<Ident>,
+    ty: Type,
+    id: Flag,
+    tenant: Flag,
+    scopes: Flag,
+    roles: Flag,
+}
 
 pub(crate) fn r#impl(input: DeriveInput) -> TokenStream {
+    let input = match SubjectInput::from_derive_input(&input) {
+        Ok(input) => input,
+        Err(err) => return err.write_errors(),
+    };
     let crate_expr = quote!(graphql_starter);
-    let ident = input.ident;
+    let ident = &input.ident;
     let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
 
-    quote!(impl #impl_generics #crate_expr::auth::Subject for #ident #ty_generics #where_clause {})
+    // Find the annotated fields
+    let mut errors = darling::Error::accumulator();
+    let (mut id, mut tenant, mut scopes, mut roles) = (None, None, None, None);
+    if let ast::Data::Struct(fields) = &input.data {
+        for (idx, field) in fields.iter().enumerate() {
+            let member = match &field.ident {
+                Some(ident) => ident.to_token_stream(),
+                None => Index::from(idx).to_token_stream(),
+            };
+            for (flag, slot, name) in [
+                (&field.id, &mut id, "id"),
+                (&field.tenant, &mut tenant, "tenant"),
+                (&field.scopes, &mut scopes, "scopes"),
+                (&field.roles, &mut roles, "roles"),
+            ] {
+                if flag.is_present() {
+                    if slot.is_some() {
+                        errors.push(
+                            darling::Error::custom(format!("Only one field can be the subject {name}"))
+                                .with_span(&flag.span()),
+                        );
+                    }
+                    *slot = Some((member.clone(), is_option(&field.ty)));
+                }
+            }
+        }
+    }
+    if let Err(err) = errors.finish() {
+        return err.write_errors();
+    }
+
+    let mut ret = quote!(impl #impl_generics #crate_expr::auth::Subject for #ident #ty_generics #where_clause {});
+
+    if let Some((member, optional)) = id {
+        let value = if optional {
+            quote!(self.#member.as_ref().map(::std::string::ToString::to_string).unwrap_or_default())
+        } else {
+            quote!(::std::string::ToString::to_string(&self.#member))
+        };
+        ret.extend(quote!(
+            impl #impl_generics #crate_expr::auth::SubjectId for #ident #ty_generics #where_clause {
+                fn subject_id(&self) -> ::std::string::String {
+                    #value
+                }
+            }
+        ));
+        if !input.skip_display.is_present() {
+            ret.extend(quote!(
+                impl #impl_generics ::std::fmt::Display for #ident #ty_generics #where_clause {
+                    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                        f.write_str(&#crate_expr::auth::SubjectId::subject_id(self))
+                    }
+                }
+            ));
+        }
+    }
+
+    if let Some((member, optional)) = tenant {
+        let value = if optional {
+            quote!(self.#member.as_ref().map(::std::string::ToString::to_string))
+        } else {
+            quote!(::std::option::Option::Some(::std::string::ToString::to_string(&self.#member)))
+        };
+        ret.extend(quote!(
+            impl #impl_generics #crate_expr::auth::SubjectTenant for #ident #ty_generics #where_clause {
+                fn tenant(&self) -> ::std::option::Option<::std::string::String> {
+                    #value
+                }
+            }
+        ));
+    }
+
+    for (field, trait_ident, fn_ident) in [
+        (scopes, quote!(SubjectScopes), quote!(scopes)),
+        (roles, quote!(SubjectRoles), quote!(roles)),
+    ] {
+        let Some((member, optional)) = field else {
+            continue;
+        };
+        let iter = if optional {
+            quote!(self.#member.iter().flatten())
+        } else {
+            quote!(self.#member.iter())
+        };
+        ret.extend(quote!(
+            impl #impl_generics #crate_expr::auth::#trait_ident for #ident #ty_generics #where_clause {
+                fn #fn_ident(&self) -> ::std::vec::Vec<&str> {
+                    #iter.map(|v| ::std::convert::AsRef::<str>::as_ref(v)).collect()
+                }
+            }
+        ));
+    }
+
+    ret
+}
+
+/// Checks whether the type is an [Option]
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path.qself.is_none() && path.path.segments.last().is_some_and(|s| s.ident == "Option"),
+        _ => false,
+    }
 }
@@ -0,0 +1,539 @@
+//! [AuthenticationService] for long-lived API keys used by machine clients.
+//!
+//! Keys have the form `{prefix}_{id}_{secret}`, where the `id` is used to look up the key on the [ApiKeyStore] and
+//! only a SHA-256 hash of the `secret` is stored.
+
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use auto_impl::auto_impl;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ring::{
+    digest,
+    rand::{SecureRandom, SystemRandom},
+};
+use ulid::Ulid;
+
+use super::{strip_bearer, AuthErrorCode, AuthenticationService, Subject};
+use crate::error::{Error, Result};
+
+/// Stored information of an API key
+#[derive(Debug, Clone)]
+pub struct ApiKeyRecord {
+    /// Public identifier of the key
+    pub id: String,
+    /// Identifier of the subject owning the key
+    pub subject: String,
+    /// SHA-256 hash of the key secret
+    pub hash: Vec<u8>,
+    /// Scopes granted to the key
+    pub scopes: Vec<String>,
+    /// When the key was created
+    pub created_at: SystemTime,
+    /// When the key was last used (if ever)
+    pub last_used_at: Option<SystemTime>,
+    /// When the key expires (if ever)
+    pub expires_at: Option<SystemTime>,
+}
+
+impl ApiKeyRecord {
+    /// Checks whether the key has expired
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= SystemTime::now())
+    }
+}
+
+/// Storage of API keys
+#[auto_impl(Box, Arc)]
+#[trait_variant::make(Send)]
+pub trait ApiKeyStore: Send + Sync + Sized + Clone + 'static {
+    /// Stores a new key
+    async fn insert(&self, record: ApiKeyRecord) -> Result<()>;
+
+    /// Retrieves a key by its id
+    async fn find(&self, id: &str) -> Result<Option<ApiKeyRecord>>;
+
+    /// Retrieves every key owned by the subject
+    async fn list(&self, subject: &str) -> Result<Vec<ApiKeyRecord>>;
+
+    /// Updates the last time the key was used
+    async fn touch(&self, id: &str, used_at: SystemTime) -> Result<()>;
+
+    /// Removes a key, returning whether it existed
+    async fn delete(&self, id: &str) -> Result<bool>;
+}
+
+/// Trait implemented by [Subject] types that can be built from an authenticated API key
+pub trait FromApiKey: Subject {
+    /// Builds the subject from the key record
+    fn from_api_key(record: &ApiKeyRecord) -> Result<Self>;
+}
+
+/// [AuthenticationService] validating API keys sent on the `x-api-key` header (or on the header configured, with or
+/// without the `Bearer` scheme).
+///
+/// Invalid, unknown or expired keys are rejected with [AuthErrorCode::AuthInvalidToken].
+///
+/// ``` rust ignore
+/// let authn = ApiKeyAuthenticationService::<MySubject, _>::new(PgApiKeyStore::new(pool)).with_prefix("myapp");
+///
+/// let (key, record) = authn.create("user:1", vec!["documents:read".into()], None).await?;
+/// ```
+pub struct ApiKeyAuthenticationService<S, St> {
+    store: St,
+    prefix: String,
+    header_name: String,
+    cookie_name: String,
+    touch_interval: Duration,
+    _subject: PhantomData<fn() -> S>,
+}
+
+impl<S, St: Clone> Clone for ApiKeyAuthenticationService<S, St> {
+    fn clone(&self) -> Self {
+        Self {
+            store: self.store.clone(),
+            prefix: self.prefix.clone(),
+            header_name: self.header_name.clone(),
+            cookie_name: self.cookie_name.clone(),
+            touch_interval: self.touch_interval,
+            _subject: PhantomData,
+        }
+    }
+}
+
+impl<S, St: ApiKeyStore> ApiKeyAuthenticationService<S, St> {
+    /// Creates a new service with the given store, using the `key` prefix and reading the keys from the `x-api-key`
+    /// header or the `api_key` cookie
+    pub fn new(store: St) -> Self {
+        Self {
+            store,
+            prefix: "key".into(),
+            header_name: "x-api-key".into(),
+            cookie_name: "api_key".into(),
+            touch_interval: Duration::from_secs(60),
+            _subject: PhantomData,
+        }
+    }
+
+    /// Modifies the prefix of the keys.
+    ///
+    /// # Panics
+    ///
+    /// If the prefix is empty or contains underscores, as they separate the parts of the keys.
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        let prefix = prefix.into();
+        assert!(
+            !prefix.is_empty() && !prefix.contains('_'),
+            "The API key prefix must be non-empty and can't contain underscores: {prefix}"
+        );
+        self.prefix = prefix;
+        self
+    }
+
+    /// Modifies the header containing the key
+    pub fn with_header_name(mut self, header_name: impl Into<String>) -> Self {
+        self.header_name = header_name.into();
+        self
+    }
+
+    /// Modifies the cookie containing the key
+    pub fn with_cookie_name(mut self, cookie_name: impl Into<String>) -> Self {
+        self.cookie_name = cookie_name.into();
+        self
+    }
+
+    /// Modifies how often the last-used timestamp is updated, one minute by default
+    pub fn with_touch_interval(mut self, touch_interval: Duration) -> Self {
+        self.touch_interval = touch_interval;
+        self
+    }
+
+    /// Retrieves the underlying store
+    pub fn store(&self) -> &St {
+        &self.store
+    }
+
+    /// Creates a new key for the subject, returning the key itself along with the stored record.
+    ///
+    /// The key can't be retrieved later, as only its hash is stored.
+    pub async fn create(
+        &self,
+        subject: impl Into<String>,
+        scopes: Vec<String>,
+        expires_at: Option<SystemTime>,
+    ) -> Result<(String, ApiKeyRecord)> {
+        let mut secret = [0u8; 32];
+        SystemRandom::new()
+            .fill(&mut secret)
+            .map_err(|_| Error::internal("Couldn't generate a random API key"))?;
+        let secret = URL_SAFE_NO_PAD.encode(secret);
+
+        let record = ApiKeyRecord {
+            id: Ulid::new().to_string().to_lowercase(),
+            subject: subject.into(),
+            hash: hash(&secret),
+            scopes,
+            created_at: SystemTime::now(),
+            last_used_at: None,
+            expires_at,
+        };
+        let key = format!("{}_{}_{secret}", self.prefix, record.id);
+        self.store.insert(record.clone()).await?;
+        Ok((key, record))
+    }
+
+    /// Revokes a key, returning whether it existed
+    pub async fn revoke(&self, id: &str) -> Result<bool> {
+        self.store.delete(id).await
+    }
+
+    /// Validates the key, returning its record
+    async fn validate(&self, key: &str) -> Result<ApiKeyRecord> {
+        let invalid = |reason: &'static str| Error::new(AuthErrorCode::AuthInvalidToken).with_reason(reason);
+
+        // Parse the key
+        let Some((id, secret)) = key
+            .strip_prefix(&self.prefix)
+            .and_then(|k| k.strip_prefix('_'))
+            .and_then(|k| k.split_once('_'))
+        else {
+            return Err(invalid("Malformed API key"));
+        };
+
+        // Look it up and validate the secret
+        let Some(mut record) = self.store.find(id).await? else {
+            return Err(invalid("Unknown API key"));
+        };
+        if !constant_time_eq(&record.hash, &hash(secret)) {
+            return Err(invalid("The API key secret doesn't match"));
+        }
+        if record.is_expired() {
+            return Err(invalid("The API key has expired"));
+        }
+
+        // Track the last usage
+        let now = SystemTime::now();
+        let outdated = record
+            .last_used_at
+            .is_none_or(|last_used_at| now.duration_since(last_used_at).unwrap_or_default() >= self.touch_interval);
+        if outdated {
+            if let Err(err) = self.store.touch(&record.id, now).await {
+                tracing::warn!("Couldn't update the API key last usage: {err}");
+            }
+            record.last_used_at = Some(now);
+        }
+
+        Ok(record)
+    }
+}
+
+impl<S: FromApiKey, St: ApiKeyStore> AuthenticationService<S> for ApiKeyAuthenticationService<S, St> {
+    fn header_name(&self) -> &str {
+        &self.header_name
+    }
+
+    fn cookie_name(&self) -> &str {
+        &self.cookie_name
+    }
+
+    fn header_scheme(&self) -> Option<&str> {
+        Some("Bearer")
+    }
+
+    async fn authenticate(&self, token: Option<&str>, cookie: Option<&str>) -> Result<S> {
+        let key = match (token, cookie) {
+            // Keep accepting the `Bearer` scheme when called directly
+            (Some(token), _) => strip_bearer(token),
+            (None, Some(cookie)) => cookie,
+            (None, None) => {
+                return Err(Error::new(AuthErrorCode::AuthMissing).with_reason("Missing API key"));
+            }
+        };
+        let record = self.validate(key).await?;
+        S::from_api_key(&record)
+    }
+}
+
+/// Hashes the key secret
+fn hash(secret: &str) -> Vec<u8> {
+    digest::digest(&digest::SHA256, secret.as_bytes()).as_ref().to_vec()
+}
+
+/// Compares both slices in constant time
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// [ApiKeyStore] keeping the keys in memory, mostly useful for tests.
+///
+/// It can be cheaply cloned, sharing the same keys.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryApiKeyStore {
+    keys: Arc<RwLock<HashMap<String, ApiKeyRecord>>>,
+}
+
+impl InMemoryApiKeyStore {
+    /// Creates a new empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ApiKeyStore for InMemoryApiKeyStore {
+    async fn insert(&self, record: ApiKeyRecord) -> Result<()> {
+        self.keys
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(record.id.clone(), record);
+        Ok(())
+    }
+
+    async fn find(&self, id: &str) -> Result<Option<ApiKeyRecord>> {
+        Ok(self.keys.read().unwrap_or_else(|e| e.into_inner()).get(id).cloned())
+    }
+
+    async fn list(&self, subject: &str) -> Result<Vec<ApiKeyRecord>> {
+        let keys = self.keys.read().unwrap_or_else(|e| e.into_inner());
+        let mut ret = keys.values().filter(|k| k.subject == subject).cloned().collect::<Vec<_>>();
+        ret.sort_by_key(|k| k.created_at);
+        Ok(ret)
+    }
+
+    async fn touch(&self, id: &str, used_at: SystemTime) -> Result<()> {
+        if let Some(record) = self.keys.write().unwrap_or_else(|e| e.into_inner()).get_mut(id) {
+            record.last_used_at = Some(used_at);
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, id: &str) -> Result<bool> {
+        Ok(self.keys.write().unwrap_or_else(|e| e.into_inner()).remove(id).is_some())
+    }
+}
+
+#[cfg(feature = "postgres")]
+pub use self::postgres::PgApiKeyStore;
+
+#[cfg(feature = "postgres")]
+mod postgres {
+    use std::time::SystemTime;
+
+    use sqlx::{
+        types::chrono::{DateTime, Utc},
+        PgPool,
+    };
+
+    use super::{ApiKeyRecord, ApiKeyStore};
+    use crate::{error::Result, sqlx::MapSqlxErr};
+
+    type ApiKeyRow = (
+        String,
+        String,
+        Vec<u8>,
+        Vec<String>,
+        DateTime<Utc>,
+        Option<DateTime<Utc>>,
+        Option<DateTime<Utc>>,
+    );
+
+    /// [ApiKeyStore] backed by a Postgres table, `api_keys` by default.
+    ///
+    /// The table can be created with [create_table](PgApiKeyStore::create_table) or with a migration like:
+    ///
+    /// ``` sql
+    /// CREATE TABLE "api_keys" (
+    ///     "id" VARCHAR(64) PRIMARY KEY,
+    ///     "subject" VARCHAR(255) NOT NULL,
+    ///     "hash" BYTEA NOT NULL,
+    ///     "scopes" TEXT[] NOT NULL,
+    ///     "created_at" TIMESTAMPTZ NOT NULL,
+    ///     "last_used_at" TIMESTAMPTZ,
+    ///     "expires_at" TIMESTAMPTZ
+    /// );
+    /// CREATE INDEX "api_keys_subject_idx" ON "api_keys" ("subject");
+    /// ```
+    #[derive(Debug, Clone)]
+    pub struct PgApiKeyStore {
+        pool: PgPool,
+        table: String,
+    }
+
+    impl PgApiKeyStore {
+        /// Creates a new store on the `api_keys` table
+        pub fn new(pool: PgPool) -> Self {
+            Self {
+                pool,
+                table: "api_keys".into(),
+            }
+        }
+
+        /// Modifies the table name
+        pub fn with_table(mut self, table: impl Into<String>) -> Self {
+            self.table = table.into();
+            self
+        }
+
+        /// Creates the table, if it doesn't exist yet
+        pub async fn create_table(&self) -> Result<()> {
+            let table = &self.table;
+            sqlx::raw_sql(&format!(
+                r#"
+                CREATE TABLE IF NOT EXISTS "{table}" (
+                    "id" VARCHAR(64) PRIMARY KEY,
+                    "subject" VARCHAR(255) NOT NULL,
+                    "hash" BYTEA NOT NULL,
+                    "scopes" TEXT[] NOT NULL,
+                    "created_at" TIMESTAMPTZ NOT NULL,
+                    "last_used_at" TIMESTAMPTZ,
+                    "expires_at" TIMESTAMPTZ
+                );
+                CREATE INDEX IF NOT EXISTS "{table}_subject_idx" ON "{table}" ("subject");
+                "#
+            ))
+            .execute(&self.pool)
+            .await
+            .map_sqlx_err("Couldn't create the API keys table")?;
+            Ok(())
+        }
+
+        fn select(&self, filter: &str) -> String {
+            format!(
+                r#"SELECT "id", "subject", "hash", "scopes", "created_at", "last_used_at", "expires_at"
+                FROM "{}" WHERE {filter}"#,
+                self.table
+            )
+        }
+    }
+
+    impl ApiKeyStore for PgApiKeyStore {
+        async fn insert(&self, record: ApiKeyRecord) -> Result<()> {
+            sqlx::query(&format!(
+                r#"INSERT INTO "{}" ("id", "subject", "hash", "scopes", "created_at", "last_used_at", "expires_at")
+                VALUES ($1, $2, $3, $4, $5, $6, $7)"#,
+                self.table
+            ))
+            .bind(record.id)
+            .bind(record.subject)
+            .bind(record.hash)
+            .bind(record.scopes)
+            .bind(DateTime::<Utc>::from(record.created_at))
+            .bind(record.last_used_at.map(DateTime::<Utc>::from))
+            .bind(record.expires_at.map(DateTime::<Utc>::from))
+            .execute(&self.pool)
+            .await
+            .map_sqlx_err("Couldn't insert the API key")?;
+            Ok(())
+        }
+
+        async fn find(&self, id: &str) -> Result<Option<ApiKeyRecord>> {
+            let row = sqlx::query_as::<_, ApiKeyRow>(&self.select(r#""id" = $1"#))
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await
+                .map_sqlx_err("Couldn't retrieve the API key")?;
+            Ok(row.map(from_row))
+        }
+
+        async fn list(&self, subject: &str) -> Result<Vec<ApiKeyRecord>> {
+            let rows = sqlx::query_as::<_, ApiKeyRow>(&self.select(r#""subject" = $1 ORDER BY "created_at""#))
+                .bind(subject)
+                .fetch_all(&self.pool)
+                .await
+                .map_sqlx_err("Couldn't list the API keys")?;
+            Ok(rows.into_iter().map(from_row).collect())
+        }
+
+        async fn touch(&self, id: &str, used_at: SystemTime) -> Result<()> {
+            sqlx::query(&format!(
+                r#"UPDATE "{}" SET "last_used_at" = $2 WHERE "id" = $1"#,
+                self.table
+            ))
+            .bind(id)
+            .bind(DateTime::<Utc>::from(used_at))
+            .execute(&self.pool)
+            .await
+            .map_sqlx_err("Couldn't update the API key")?;
+            Ok(())
+        }
+
+        async fn delete(&self, id: &str) -> Result<bool> {
+            let res = sqlx::query(&format!(r#"DELETE FROM "{}" WHERE "id" = $1"#, self.table))
+                .bind(id)
+                .execute(&self.pool)
+                .await
+                .map_sqlx_err("Couldn't delete the API key")?;
+            Ok(res.rows_affected() > 0)
+        }
+    }
+
+    fn from_row((id, subject, hash, scopes, created_at, last_used_at, expires_at): ApiKeyRow) -> ApiKeyRecord {
+        ApiKeyRecord {
+            id,
+            subject,
+            hash,
+            scopes,
+            created_at: created_at.into(),
+            last_used_at: last_used_at.map(Into::into),
+            expires_at: expires_at.map(Into::into),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{test_support::TestSubject, AuthCredentials};
+
+    #[tokio::test]
+    async fn test_api_key_authentication() {
+        let store = InMemoryApiKeyStore::new();
+        let authn = ApiKeyAuthenticationService::<TestSubject, _>::new(store.clone()).with_prefix("test");
+
+        let (key, record) = authn.create("user:1", vec!["documents:read".into()], None).await.unwrap();
+        assert!(key.starts_with(&format!("test_{}_", record.id)));
+        assert_ne!(record.hash, key.as_bytes());
+
+        let subject = authn.authenticate(Some(&key), None).await.unwrap();
+        assert_eq!(subject.id, "user:1");
+        assert_eq!(subject.scopes, ["documents:read"]);
+        let subject = authn.authenticate(Some(&format!("Bearer {key}")), None).await.unwrap();
+        assert_eq!(subject.id, "user:1");
+        let credentials = AuthCredentials::new().with_token("x-api-key", format!("Bearer {key}"));
+        let subject = authn.authenticate_credentials(&credentials).await.unwrap();
+        assert_eq!(subject.id, "user:1");
+        assert!(store.find(&record.id).await.unwrap().unwrap().last_used_at.is_some());
+
+        let invalid = [
+            format!("{key}x"),
+            key.replacen("test_", "other_", 1),
+            format!("test_{}_secret", Ulid::new().to_string().to_lowercase()),
+            "malformed".to_owned(),
+        ];
+        for key in invalid {
+            let err = authn.authenticate(Some(&key), None).await.unwrap_err();
+            assert_eq!(err.info().code(), "AUTH_INVALID_TOKEN");
+        }
+
+        // Expired keys are rejected
+        let (expired, _) = authn
+            .create("user:1", Vec::new(), Some(SystemTime::now() - Duration::from_secs(1)))
+            .await
+            .unwrap();
+        assert!(authn.authenticate(Some(&expired), None).await.is_err());
+        assert_eq!(store.list("user:1").await.unwrap().len(), 2);
+
+        // Revoked keys are rejected
+        assert!(authn.revoke(&record.id).await.unwrap());
+        assert!(authn.authenticate(Some(&key), None).await.is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "can't contain underscores")]
+    fn test_api_key_prefix_underscore() {
+        ApiKeyAuthenticationService::<TestSubject, _>::new(InMemoryApiKeyStore::new()).with_prefix("my_app");
+    }
+}
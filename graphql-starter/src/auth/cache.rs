@@ -0,0 +1,180 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use axum::extract::FromRequestParts;
+use http::{request::Parts, StatusCode};
+use tokio::sync::OnceCell;
+
+use super::{AuthErrorCode, AuthorizationService, Subject};
+use crate::error::{Error, Result};
+
+/// Request-scoped cache of authorization decisions, keyed by subject, relation and object.
+///
+/// Only allowed and [FORBIDDEN](StatusCode::FORBIDDEN) decisions are cached, any other error is returned without
+/// caching it. Denied decisions are returned from the cache as [AuthFailed](AuthErrorCode::AuthFailed) errors.
+/// Concurrent lookups of the same decision wait for a single call to the authorization service.
+///
+/// It's included on the GraphQL context by the `graphql_batch_handler`, used by the `AuthGuard`, and can be extracted
+/// on axum handlers, sharing the same cache for every extraction on the same request. Hits and misses are traced and
+/// summarized when the request completes.
+///
+/// It can be cheaply cloned, sharing the same decisions.
+#[derive(Debug, Clone, Default)]
+pub struct AuthzCache(Arc<AuthzCacheInner>);
+
+#[derive(Debug, Default)]
+struct AuthzCacheInner {
+    decisions: Mutex<HashMap<(String, String, String), Arc<OnceCell<bool>>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl AuthzCache {
+    /// Creates a new empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Validates if the _subject_ is allowed to perform the _relation_ on the _object_, calling the authorization
+    /// service only if the decision is not cached yet
+    pub async fn authorize<S: Subject>(
+        &self,
+        authz: &impl AuthorizationService<S>,
+        subject: &S,
+        relation: &str,
+        object: &str,
+    ) -> Result<()> {
+        let key = (subject.to_string(), relation.to_owned(), object.to_owned());
+        let cell = self
+            .0
+            .decisions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(key)
+            .or_default()
+            .clone();
+
+        // Only one of the concurrent lookups calls the service, if it fails without a decision the next one retries
+        let mut called = false;
+        let mut denied = None;
+        let allowed = *cell
+            .get_or_try_init(|| async {
+                called = true;
+                let misses = self.0.misses.fetch_add(1, Ordering::Relaxed) + 1;
+                tracing::trace!(misses, "Authorization cache miss for '{subject}#{relation}@{object}'");
+                match authz.authorize(subject, relation, object).await {
+                    Ok(()) => Ok(true),
+                    Err(err) if err.info().status() == StatusCode::FORBIDDEN => {
+                        denied = Some(err);
+                        Ok(false)
+                    }
+                    Err(err) => Err(err),
+                }
+            })
+            .await?;
+
+        // The lookup that called the service returns its original error
+        if let Some(err) = denied {
+            return Err(err);
+        }
+        if !called {
+            let hits = self.0.hits.fetch_add(1, Ordering::Relaxed) + 1;
+            tracing::trace!(hits, "Authorization cache hit for '{subject}#{relation}@{object}'");
+        }
+        if allowed {
+            Ok(())
+        } else {
+            Err(Error::new(AuthErrorCode::AuthFailed)
+                .with_reason(format!("'{subject}' is not allowed to '{relation}' on '{object}'")))
+        }
+    }
+
+    /// Retrieves the number of decisions returned from the cache
+    pub fn hits(&self) -> u64 {
+        self.0.hits.load(Ordering::Relaxed)
+    }
+
+    /// Retrieves the number of decisions not cached yet
+    pub fn misses(&self) -> u64 {
+        self.0.misses.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for AuthzCacheInner {
+    fn drop(&mut self) {
+        let hits = *self.hits.get_mut();
+        let misses = *self.misses.get_mut();
+        if hits + misses > 0 {
+            tracing::debug!(hits, misses, "Authorization cache summary");
+        }
+    }
+}
+
+impl<St: Send + Sync> FromRequestParts<St> for AuthzCache {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &St) -> Result<Self, Self::Rejection> {
+        Ok(parts.extensions.get_or_insert_default::<AuthzCache>().clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::test_support::TestSubject;
+
+    /// Service allowing the `allowed` object and counting the calls
+    #[derive(Clone, Default)]
+    struct TestService(Arc<AtomicU64>);
+
+    impl AuthorizationService<TestSubject> for TestService {
+        async fn authorize(&self, _subject: &TestSubject, _relation: &str, object: &str) -> Result<()> {
+            tokio::task::yield_now().await;
+            self.0.fetch_add(1, Ordering::Relaxed);
+            if object == "allowed" {
+                Ok(())
+            } else {
+                Err(Error::new(AuthErrorCode::AuthFailed))
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_authz_cache() {
+        let subject = TestSubject::new("user:1");
+        let authz = TestService::default();
+        let cache = AuthzCache::new();
+        for _ in 0..3 {
+            cache.authorize(&authz, &subject, "view", "allowed").await.unwrap();
+            let err = cache.authorize(&authz, &subject, "view", "denied").await.unwrap_err();
+            assert_eq!(err.info().code(), "AUTH_FAILED");
+        }
+        assert_eq!(authz.0.load(Ordering::Relaxed), 2);
+        assert_eq!(cache.hits(), 4);
+        assert_eq!(cache.misses(), 2);
+
+        // Extractions on the same request share the cache
+        let (mut parts, _) = http::Request::new(()).into_parts();
+        let first = AuthzCache::from_request_parts(&mut parts, &()).await.unwrap();
+        first.authorize(&authz, &subject, "view", "allowed").await.unwrap();
+        let second = AuthzCache::from_request_parts(&mut parts, &()).await.unwrap();
+        assert_eq!(second.misses(), 1);
+
+        // Concurrent lookups call the service once
+        let authz = TestService::default();
+        let cache = AuthzCache::new();
+        let lookups = (0..3).map(|_| cache.authorize(&authz, &subject, "view", "allowed"));
+        for res in futures_util::future::join_all(lookups).await {
+            res.unwrap();
+        }
+        assert_eq!(authz.0.load(Ordering::Relaxed), 1);
+        assert_eq!(cache.hits(), 2);
+        assert_eq!(cache.misses(), 1);
+    }
+}
@@ -0,0 +1,277 @@
+use std::{future::Future, pin::Pin, sync::Arc};
+
+use super::{expired_cookie, AuthCredentials, AuthErrorCode, Authenticated, AuthenticationService, Subject};
+use crate::error::{Error, Result};
+
+/// Trait implemented by [Subject] types that record the scheme they were authenticated with
+pub trait WithAuthScheme: Subject {
+    /// Records the name of the scheme that authenticated the subject
+    fn with_auth_scheme(self, scheme: &'static str) -> Self;
+}
+
+/// [AuthenticationService] combining many others, each one identified by a scheme name.
+///
+/// Services are tried in the same order they were added, skipping the ones without credentials on the request. The
+/// first one to succeed authenticates the subject, recording its scheme. If every one of them fails, the most specific
+/// error is returned: server errors first, then any other client error, malformed credentials, invalid tokens and
+/// finally missing authentication. On ties, the error of the first service is returned.
+///
+/// The [header_name](AuthenticationService::header_name) and [cookie_name](AuthenticationService::cookie_name) are the
+/// ones of the first service, but the credentials of every service are extracted by the [Auth](super::Auth) extractor
+/// and the GraphQL subscription handler.
+///
+/// ``` rust ignore
+/// let authn = CompositeAuthenticationService::new()
+///     .with_scheme("bearer", jwt_service)
+///     .with_scheme("session", session_service)
+///     .with_scheme("api-key", api_key_service);
+/// ```
+pub struct CompositeAuthenticationService<S> {
+    services: Vec<(&'static str, Arc<dyn DynAuthenticationService<S>>)>,
+}
+
+impl<S> Clone for CompositeAuthenticationService<S> {
+    fn clone(&self) -> Self {
+        Self {
+            services: self.services.clone(),
+        }
+    }
+}
+
+impl<S> Default for CompositeAuthenticationService<S> {
+    fn default() -> Self {
+        Self { services: Vec::new() }
+    }
+}
+
+impl<S: Subject> CompositeAuthenticationService<S> {
+    /// Creates a new service without inner services, which fails to authenticate any request
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a service to be tried after the previous ones, identified by the given scheme
+    pub fn with_scheme(mut self, scheme: &'static str, service: impl AuthenticationService<S>) -> Self {
+        self.services.push((scheme, Arc::new(service)));
+        self
+    }
+
+    /// Retrieves the schemes of the inner services, in order
+    pub fn schemes(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.services.iter().map(|(scheme, _)| *scheme)
+    }
+}
+
+impl<S: WithAuthScheme> AuthenticationService<S> for CompositeAuthenticationService<S> {
+    fn header_name(&self) -> &str {
+        self.services
+            .first()
+            .map(|(_, s)| s.dyn_header_name())
+            .unwrap_or(http::header::AUTHORIZATION.as_str())
+    }
+
+    fn cookie_name(&self) -> &str {
+        self.services.first().map(|(_, s)| s.dyn_cookie_name()).unwrap_or_default()
+    }
+
+    fn header_names(&self) -> Vec<&str> {
+        let mut ret = Vec::new();
+        for name in self.services.iter().flat_map(|(_, s)| s.dyn_header_names()) {
+            if !ret.iter().any(|n: &&str| n.eq_ignore_ascii_case(name)) {
+                ret.push(name);
+            }
+        }
+        ret
+    }
+
+    fn cookie_names(&self) -> Vec<&str> {
+        let mut ret = Vec::new();
+        for name in self.services.iter().flat_map(|(_, s)| s.dyn_cookie_names()) {
+            if !ret.contains(&name) {
+                ret.push(name);
+            }
+        }
+        ret
+    }
+
+    fn removal_cookie(&self, name: &str) -> String {
+        // Delegate to the service owning the cookie, which knows its attributes
+        match self.services.iter().find(|(_, s)| s.dyn_cookie_names().contains(&name)) {
+            Some((_, service)) => service.dyn_removal_cookie(name),
+            None => expired_cookie(name),
+        }
+    }
+
+    async fn authenticate(&self, token: Option<&str>, cookie: Option<&str>) -> Result<S> {
+        let mut credentials = AuthCredentials::new();
+        if let Some(token) = token {
+            credentials = credentials.with_token(self.header_name(), token);
+        }
+        if let Some(cookie) = cookie {
+            credentials = credentials.with_cookie(self.cookie_name(), cookie);
+        }
+        self.authenticate_credentials(&credentials).await
+    }
+
+    async fn authenticate_credentials(&self, credentials: &AuthCredentials) -> Result<S> {
+        Ok(self.authenticate_with_effects(credentials).await?.subject)
+    }
+
+    async fn authenticate_with_effects(&self, credentials: &AuthCredentials) -> Result<Authenticated<S>> {
+        let mut error: Option<Box<Error>> = None;
+        for (scheme, service) in &self.services {
+            // Skip services without credentials
+            let has_credentials = service.dyn_header_names().iter().any(|h| credentials.token(h).is_some())
+                || service.dyn_cookie_names().iter().any(|c| credentials.cookie(c).is_some());
+            if !has_credentials {
+                continue;
+            }
+
+            match service.dyn_authenticate_with_effects(credentials).await {
+                Ok(mut authenticated) => {
+                    tracing::trace!("Authenticated with '{scheme}' scheme");
+                    authenticated.subject = authenticated.subject.with_auth_scheme(scheme);
+                    return Ok(authenticated);
+                }
+                Err(err) => {
+                    tracing::debug!("Couldn't authenticate with '{scheme}' scheme: {err}");
+                    if error.as_ref().is_none_or(|e| specificity(&err) > specificity(e)) {
+                        error = Some(err);
+                    }
+                }
+            }
+        }
+        Err(error.unwrap_or_else(|| Error::new(AuthErrorCode::AuthMissing).with_reason("Missing credentials")))
+    }
+}
+
+/// Ranks how specific an authentication error is
+fn specificity(err: &Error) -> u8 {
+    let code = err.info().code();
+    if err.info().status().is_server_error() {
+        4
+    } else if code == "AUTH_MISSING" {
+        0
+    } else if code == "AUTH_INVALID_TOKEN" {
+        1
+    } else if code.starts_with("AUTH_MALFORMED") {
+        2
+    } else {
+        3
+    }
+}
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Object-safe version of [AuthenticationService]
+trait DynAuthenticationService<S>: Send + Sync + 'static {
+    fn dyn_header_name(&self) -> &str;
+    fn dyn_cookie_name(&self) -> &str;
+    fn dyn_header_names(&self) -> Vec<&str>;
+    fn dyn_cookie_names(&self) -> Vec<&str>;
+    fn dyn_removal_cookie(&self, name: &str) -> String;
+    fn dyn_authenticate_with_effects<'a>(
+        &'a self,
+        credentials: &'a AuthCredentials,
+    ) -> BoxFuture<'a, Result<Authenticated<S>>>;
+}
+
+impl<S: Subject, T: AuthenticationService<S>> DynAuthenticationService<S> for T {
+    fn dyn_header_name(&self) -> &str {
+        AuthenticationService::header_name(self)
+    }
+
+    fn dyn_cookie_name(&self) -> &str {
+        AuthenticationService::cookie_name(self)
+    }
+
+    fn dyn_header_names(&self) -> Vec<&str> {
+        AuthenticationService::header_names(self)
+    }
+
+    fn dyn_cookie_names(&self) -> Vec<&str> {
+        AuthenticationService::cookie_names(self)
+    }
+
+    fn dyn_removal_cookie(&self, name: &str) -> String {
+        AuthenticationService::removal_cookie(self, name)
+    }
+
+    fn dyn_authenticate_with_effects<'a>(
+        &'a self,
+        credentials: &'a AuthCredentials,
+    ) -> BoxFuture<'a, Result<Authenticated<S>>> {
+        Box::pin(AuthenticationService::authenticate_with_effects(self, credentials))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{auth::test_support::TestSubject, error::GenericErrorCode};
+
+    /// Service accepting the `valid` token, failing with an internal error on the `down` token
+    #[derive(Clone)]
+    struct TestService {
+        header_name: &'static str,
+        cookie_name: &'static str,
+    }
+
+    impl AuthenticationService<TestSubject> for TestService {
+        fn header_name(&self) -> &str {
+            self.header_name
+        }
+
+        fn cookie_name(&self) -> &str {
+            self.cookie_name
+        }
+
+        async fn authenticate(&self, token: Option<&str>, cookie: Option<&str>) -> Result<TestSubject> {
+            match token.or(cookie) {
+                Some("valid") => Ok(TestSubject::new(self.header_name)),
+                Some("down") => Err(Error::new(GenericErrorCode::ServiceUnavailable)),
+                Some(_) => Err(Error::new(AuthErrorCode::AuthInvalidToken)),
+                None => Err(Error::new(AuthErrorCode::AuthMissing)),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_composite_authentication() {
+        let authn = CompositeAuthenticationService::new()
+            .with_scheme(
+                "bearer",
+                TestService {
+                    header_name: "Authorization",
+                    cookie_name: "token",
+                },
+            )
+            .with_scheme(
+                "api-key",
+                TestService {
+                    header_name: "x-api-key",
+                    cookie_name: "token",
+                },
+            );
+        assert_eq!(authn.header_names(), ["Authorization", "x-api-key"]);
+        assert_eq!(authn.cookie_names(), ["token"]);
+
+        // The first service with valid credentials authenticates the subject
+        let credentials = AuthCredentials::new()
+            .with_token("authorization", "invalid")
+            .with_token("x-api-key", "valid");
+        let subject = authn.authenticate_credentials(&credentials).await.unwrap();
+        assert_eq!(subject.id, "x-api-key");
+        assert_eq!(subject.scheme, "api-key");
+
+        // The most specific error is returned
+        let credentials = AuthCredentials::new()
+            .with_token("authorization", "invalid")
+            .with_token("x-api-key", "down");
+        let err = authn.authenticate_credentials(&credentials).await.unwrap_err();
+        assert_eq!(err.info().code(), "SERVICE_UNAVAILABLE");
+
+        let err = authn.authenticate_credentials(&AuthCredentials::new()).await.unwrap_err();
+        assert_eq!(err.info().code(), "AUTH_MISSING");
+    }
+}
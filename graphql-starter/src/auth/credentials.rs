@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+
+use http::HeaderMap;
+
+use super::AuthErrorCode;
+use crate::error::{err, MapToErr, Result};
+
+/// Credentials sent by the client to authenticate: tokens keyed by their (lowercase) header name and cookies keyed by
+/// their name.
+#[derive(Debug, Clone, Default)]
+pub struct AuthCredentials {
+    tokens: HashMap<String, String>,
+    cookies: HashMap<String, String>,
+}
+
+impl AuthCredentials {
+    /// Creates new empty credentials
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Extracts the credentials from the request headers, including only the given headers and cookies
+    pub fn from_headers<'a>(
+        headers: &HeaderMap,
+        header_names: impl IntoIterator<Item = &'a str>,
+        cookie_names: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Self> {
+        let mut ret = Self::new();
+
+        // Extract the auth headers
+        for header_name in header_names {
+            if let Some(value) = headers.get(header_name) {
+                let value = value.to_str().map_err(|err| {
+                    err!(
+                        AuthErrorCode::AuthMalformedAuthHeader {
+                            auth_header: header_name.into(),
+                        },
+                        "Couldn't parse auth header value"
+                    )
+                    .with_error_source(err)
+                })?;
+                ret = ret.with_token(header_name, value);
+            }
+        }
+
+        // Extract the auth cookies, keeping the first one when repeated
+        let cookie_names = cookie_names.into_iter().collect::<Vec<_>>();
+        if !cookie_names.is_empty() {
+            for (name, value) in parse_cookies(headers)? {
+                if cookie_names.contains(&name) && ret.cookie(name).is_none() {
+                    ret = ret.with_cookie(name, value);
+                }
+            }
+        }
+
+        Ok(ret)
+    }
+
+    /// Includes a token, empty values are ignored
+    pub fn with_token(mut self, header_name: &str, value: impl Into<String>) -> Self {
+        let value = value.into();
+        if !value.is_empty() {
+            self.tokens.insert(header_name.to_lowercase(), value);
+        }
+        self
+    }
+
+    /// Includes a cookie, empty values are ignored
+    pub fn with_cookie(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        let value = value.into();
+        if !value.is_empty() {
+            self.cookies.insert(name.into(), value);
+        }
+        self
+    }
+
+    /// Retrieves the token sent on the given header (if any)
+    pub fn token(&self, header_name: &str) -> Option<&str> {
+        self.tokens.get(&header_name.to_lowercase()).map(String::as_str)
+    }
+
+    /// Retrieves the authorization scheme of the token sent on the given header (if any), like `Bearer`
+    pub fn scheme(&self, header_name: &str) -> Option<&str> {
+        self.token(header_name).and_then(|t| split_auth_scheme(t).0)
+    }
+
+    /// Retrieves the token sent on the given header (if any) without the expected authorization scheme.
+    ///
+    /// Tokens without a scheme are returned as they are, but a different scheme fails with
+    /// [AuthMalformedAuthHeader](AuthErrorCode::AuthMalformedAuthHeader).
+    pub fn token_with_scheme(&self, header_name: &str, expected_scheme: Option<&str>) -> Result<Option<&str>> {
+        let Some(token) = self.token(header_name) else {
+            return Ok(None);
+        };
+        let Some(expected_scheme) = expected_scheme else {
+            return Ok(Some(token));
+        };
+        match split_auth_scheme(token) {
+            (Some(scheme), credentials) if scheme.eq_ignore_ascii_case(expected_scheme) => Ok(Some(credentials)),
+            (Some(scheme), _) => Err(err!(
+                AuthErrorCode::AuthMalformedAuthHeader {
+                    auth_header: header_name.into(),
+                },
+                "Unexpected '{}' authorization scheme, '{}' required",
+                scheme,
+                expected_scheme
+            )),
+            (None, token) => Ok(Some(token)),
+        }
+    }
+
+    /// Retrieves the value of the given cookie (if any)
+    pub fn cookie(&self, name: &str) -> Option<&str> {
+        self.cookies.get(name).map(String::as_str)
+    }
+
+    /// Retrieves the names of the cookies included
+    pub fn cookie_names(&self) -> impl Iterator<Item = &str> {
+        self.cookies.keys().map(String::as_str)
+    }
+
+    /// Checks whether there are no credentials at all
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty() && self.cookies.is_empty()
+    }
+}
+
+/// Parses the cookies of every `Cookie` header, as defined by
+/// [RFC 6265](https://datatracker.ietf.org/doc/html/rfc6265#section-5.4).
+///
+/// Whitespace around names and values is ignored, quoted values are unquoted and pairs without a name are skipped.
+/// Cookies are returned in the same order they were sent, including the repeated ones.
+pub fn parse_cookies(headers: &HeaderMap) -> Result<Vec<(&str, &str)>> {
+    let mut ret = Vec::new();
+    for header in headers.get_all(http::header::COOKIE) {
+        let header = header
+            .to_str()
+            .map_to_err_with(AuthErrorCode::AuthMalformedCookies, "Couldn't parse request cookies")?;
+        for pair in header.split(';') {
+            let Some((name, value)) = pair.split_once('=') else {
+                continue;
+            };
+            let name = name.trim();
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(value);
+            if !name.is_empty() {
+                ret.push((name, value));
+            }
+        }
+    }
+    Ok(ret)
+}
+
+/// Splits the authorization scheme from the credentials of an auth header value, like `Bearer {token}`, as defined by
+/// [RFC 9110](https://datatracker.ietf.org/doc/html/rfc9110#section-11.4).
+///
+/// Values without a scheme are returned as they are.
+pub fn split_auth_scheme(value: &str) -> (Option<&str>, &str) {
+    let value = value.trim();
+    match value.split_once(' ') {
+        Some((scheme, credentials)) if !scheme.is_empty() && scheme.chars().all(is_tchar) => {
+            (Some(scheme), credentials.trim_start())
+        }
+        _ => (None, value),
+    }
+}
+
+/// Removes the `Bearer` scheme from the token, if present
+#[cfg(any(feature = "api-key", feature = "jwt"))]
+pub(crate) fn strip_bearer(token: &str) -> &str {
+    match split_auth_scheme(token) {
+        (Some(scheme), credentials) if scheme.eq_ignore_ascii_case("bearer") => credentials,
+        _ => token,
+    }
+}
+
+/// Checks whether the char is allowed on a token
+fn is_tchar(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use http::{header::COOKIE, HeaderValue};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_credentials() {
+        let mut headers = HeaderMap::new();
+        headers.append(COOKIE, HeaderValue::from_static("theme=dark;token=first"));
+        headers.append(COOKIE, HeaderValue::from_static(" session = \"abc\" ; invalid; token=second;=empty"));
+        headers.append("authorization", HeaderValue::from_static("bearer  xyz"));
+
+        let cookies = parse_cookies(&headers).unwrap();
+        assert_eq!(
+            cookies,
+            [("theme", "dark"), ("token", "first"), ("session", "abc"), ("token", "second")]
+        );
+
+        let credentials =
+            AuthCredentials::from_headers(&headers, ["Authorization"], ["token", "session", "other"]).unwrap();
+        assert_eq!(credentials.cookie("token"), Some("first"));
+        assert_eq!(credentials.cookie("session"), Some("abc"));
+        assert_eq!(credentials.cookie("theme"), None);
+        assert_eq!(credentials.scheme("authorization"), Some("bearer"));
+        assert_eq!(credentials.token_with_scheme("authorization", None).unwrap(), Some("bearer  xyz"));
+        assert_eq!(credentials.token_with_scheme("authorization", Some("Bearer")).unwrap(), Some("xyz"));
+        let err = credentials.token_with_scheme("authorization", Some("Basic")).unwrap_err();
+        assert_eq!(err.info().code(), "AUTH_MALFORMED_AUTH_HEADER");
+
+        assert_eq!(split_auth_scheme("eyJhbGciOi.eyJzdWIi.c2ln"), (None, "eyJhbGciOi.eyJzdWIi.c2ln"));
+        assert_eq!(split_auth_scheme("ApiKey key"), (Some("ApiKey"), "key"));
+        assert_eq!(split_auth_scheme("not/a scheme"), (None, "not/a scheme"));
+    }
+}
@@ -15,4 +15,6 @@ pub enum AuthErrorCode {
     AuthInvalidToken,
     #[error(status = StatusCode::FORBIDDEN, message = "The user is not allowed to perform such action")]
     AuthFailed,
+    #[error(status = StatusCode::FORBIDDEN, message = "Insufficient scope, \"{scope}\" required")]
+    AuthInsufficientScope { scope: String },
 }
@@ -1,15 +1,22 @@
 use axum::extract::{FromRequestParts, OptionalFromRequestParts};
 use http::request::Parts;
 
-use super::{AuthErrorCode, AuthState, AuthenticationService, Subject};
-use crate::error::{err, ApiError, MapToErr, OkOrErr, Result};
+use super::{AuthCredentials, AuthErrorCode, AuthResponseHeaders, AuthState, AuthenticationService, Subject};
+use crate::error::{ApiError, OkOrErr, ReportedSubject, Result};
 
 /// This extractor will authenticate the request by inspecting both the authentication header and cookie.
 ///
 /// It also implements [OptionalFromRequestParts] so it can be optionally extracted, returning [None] if there is no
 /// auth header or cookie, but failing if they're present but not valid.
+///
+/// The authenticated subject is kept on the request extensions, so extracting it again (i.e. on a layer and then on the
+/// handler) doesn't authenticate the request twice.
 pub struct Auth<S: Subject>(pub S);
 
+/// Subject already authenticated for the request
+#[derive(Clone)]
+struct AuthenticatedSubject<S>(S);
+
 impl<S, St> OptionalFromRequestParts<St> for Auth<S>
 where
     S: Subject,
@@ -18,63 +25,46 @@ where
     type Rejection = Box<ApiError>;
 
     async fn from_request_parts(parts: &mut Parts, state: &St) -> Result<Option<Self>, Self::Rejection> {
-        // Extract the auth header (if any)
-        let auth_header_name = state.authn().header_name();
-        let auth_token = parts
-            .headers
-            .get(auth_header_name)
-            .map(|v| {
-                v.to_str().map_err(|err| {
-                    err!(
-                        AuthErrorCode::AuthMalformedAuthHeader {
-                            auth_header: auth_header_name.into(),
-                        },
-                        "Couldn't parse auth header value"
-                    )
-                    .with_source(err)
-                })
-            })
-            .transpose()?
-            .filter(|t| !t.is_empty());
+        // Reuse the subject if the request was already authenticated
+        if let Some(AuthenticatedSubject(subject)) = parts.extensions.get::<AuthenticatedSubject<S>>() {
+            return Ok(Some(Self(subject.clone())));
+        }
 
-        // Extract the auth cookie (if any)
-        let auth_cookie_name = state.authn().cookie_name();
-        let auth_cookie_value = parts
-            .headers
-            .get(http::header::COOKIE)
-            .map(|v| {
-                v.to_str()
-                    .map_to_err_with(AuthErrorCode::AuthMalformedCookies, "Couldn't parse request cookies")
-            })
-            .transpose()?
-            .and_then(|cookies| {
-                cookies
-                    .split("; ")
-                    .find_map(|cookie| cookie.strip_prefix(&format!("{auth_cookie_name}=")))
-            })
-            .filter(|c| !c.is_empty());
+        // Extract the auth headers and cookies (if any)
+        let authn = state.authn();
+        let credentials = AuthCredentials::from_headers(&parts.headers, authn.header_names(), authn.cookie_names())?;
 
         // Authenticate the subject
-        if auth_token.is_none() && auth_cookie_value.is_none() {
+        if credentials.is_empty() {
             Ok(None)
         } else {
-            let subject = match state.authn().authenticate(auth_token, auth_cookie_value).await {
-                Ok(s) => s,
+            let authenticated = match authn.authenticate_with_effects(&credentials).await {
+                Ok(a) => a,
                 Err(err) => {
                     let is_invalid_token = err.info().code() == "AUTH_INVALID_TOKEN";
                     let mut err: Box<ApiError> = err.into();
-                    // If the authentication fails because the token is invalid, remove the auth cookie if set
+                    // If the authentication fails because the token is invalid, remove the auth cookies if set
                     // If the cookie is HttpOnly, clients are not able to remove it manually when invalid
-                    if auth_cookie_value.is_some() && is_invalid_token {
-                        err = err.with_header(
-                            "Set-Cookie",
-                            format!("{auth_cookie_name}=invalid; Expires=Thu, 01 Jan 1970 00:00:00 GMT"),
-                        );
+                    if is_invalid_token {
+                        for cookie_name in credentials.cookie_names() {
+                            err = err.with_header("Set-Cookie", authn.removal_cookie(cookie_name));
+                        }
                     }
                     return Err(err);
                 }
             };
+            let subject = authenticated.subject;
             tracing::trace!("Authenticated as {subject}");
+            if !authenticated.headers.is_empty() {
+                match parts.extensions.get::<AuthResponseHeaders>() {
+                    Some(slot) => slot.append(authenticated.headers),
+                    None => tracing::trace!("Dropping authentication response headers, missing AuthResponseLayer"),
+                }
+            }
+            if let Some(reported_subject) = parts.extensions.get::<ReportedSubject>() {
+                reported_subject.set(subject.to_string());
+            }
+            parts.extensions.insert(AuthenticatedSubject(subject.clone()));
             Ok(Some(Self(subject)))
         }
     }
@@ -1,13 +1,43 @@
-use std::fmt;
+use std::{fmt, future::Future};
 
 use auto_impl::auto_impl;
+use http::StatusCode;
 
-use crate::error::Result;
+use super::{AuthCredentials, Authenticated};
+use crate::error::{Error, Result};
 
 /// Trait to identify authenticated subjects
 #[auto_impl(Box, Arc)]
 pub trait Subject: fmt::Display + Send + Sync + Sized + Clone + 'static {}
 
+/// Trait implemented by [Subject] types exposing their identifier
+#[auto_impl(Box, Arc)]
+pub trait SubjectId: Subject {
+    /// Retrieves the identifier of the subject
+    fn subject_id(&self) -> String;
+}
+
+/// Trait implemented by [Subject] types that might belong to a tenant
+#[auto_impl(Box, Arc)]
+pub trait SubjectTenant: Subject {
+    /// Retrieves the tenant of the subject (if any)
+    fn tenant(&self) -> Option<String>;
+}
+
+/// Trait implemented by [Subject] types with granted scopes
+#[auto_impl(Box, Arc)]
+pub trait SubjectScopes: Subject {
+    /// Retrieves the scopes granted to the subject
+    fn scopes(&self) -> Vec<&str>;
+}
+
+/// Trait implemented by [Subject] types with assigned roles
+#[auto_impl(Box, Arc)]
+pub trait SubjectRoles: Subject {
+    /// Retrieves the roles assigned to the subject
+    fn roles(&self) -> Vec<&str>;
+}
+
 /// Authentication service
 #[auto_impl(Box, Arc)]
 #[trait_variant::make(Send)]
@@ -20,6 +50,59 @@ pub trait AuthenticationService<S: Subject>: Send + Sync + Sized + Clone + 'stat
 
     /// Validates if the given token or cookie is valid and returns the authenticated subject
     async fn authenticate(&self, token: Option<&str>, cookie: Option<&str>) -> Result<S>;
+
+    /// Names of every header that might contain authentication tokens, by default just the
+    /// [header_name](AuthenticationService::header_name)
+    fn header_names(&self) -> Vec<&str> {
+        vec![self.header_name()]
+    }
+
+    /// Names of every cookie that might contain authentication tokens, by default just the
+    /// [cookie_name](AuthenticationService::cookie_name)
+    fn cookie_names(&self) -> Vec<&str> {
+        vec![self.cookie_name()]
+    }
+
+    /// Authorization scheme expected on the [header](AuthenticationService::header_name), like `Bearer`, by default
+    /// none.
+    ///
+    /// When set, the scheme is removed from the token before [authenticating](AuthenticationService::authenticate).
+    fn header_scheme(&self) -> Option<&str> {
+        None
+    }
+
+    /// Builds the `Set-Cookie` header value to remove the given authentication cookie from the client, when its token
+    /// is invalid.
+    ///
+    /// By default, the cookie is expired without any other attribute, so it's only removed when set on the root path.
+    fn removal_cookie(&self, name: &str) -> String {
+        expired_cookie(name)
+    }
+
+    /// Validates the credentials sent by the client and returns the authenticated subject.
+    ///
+    /// By default, it [authenticates](AuthenticationService::authenticate) the token and cookie with the configured
+    /// names, removing the [scheme](AuthenticationService::header_scheme) from the token.
+    fn authenticate_credentials(&self, credentials: &AuthCredentials) -> impl Future<Output = Result<S>> + Send {
+        async move {
+            let token = credentials.token_with_scheme(self.header_name(), self.header_scheme())?;
+            self.authenticate(token, credentials.cookie(self.cookie_name())).await
+        }
+    }
+
+    /// Validates the credentials sent by the client and returns the authenticated subject, along with the headers to
+    /// include on the response, like refreshed tokens or renewed cookies (see [AuthResponseLayer]).
+    ///
+    /// By default, it [authenticates the credentials](AuthenticationService::authenticate_credentials) without any
+    /// response header.
+    ///
+    /// [AuthResponseLayer]: super::AuthResponseLayer
+    fn authenticate_with_effects(
+        &self,
+        credentials: &AuthCredentials,
+    ) -> impl Future<Output = Result<Authenticated<S>>> + Send {
+        async move { Ok(Authenticated::new(self.authenticate_credentials(credentials).await?)) }
+    }
 }
 
 /// Authorization service
@@ -28,6 +111,46 @@ pub trait AuthenticationService<S: Subject>: Send + Sync + Sized + Clone + 'stat
 pub trait AuthorizationService<S: Subject>: Send + Sync + Sized + Clone + 'static {
     /// Validates if the _subject_ is allowed to perform the _relation_ on the _object_
     async fn authorize(&self, subject: &S, relation: &str, object: &str) -> Result<()>;
+
+    /// Validates which of the _objects_ the _subject_ is allowed to perform the _relation_ on, returning whether each
+    /// one of them is allowed, in the same order.
+    ///
+    /// By default, it [authorizes](AuthorizationService::authorize) every object sequentially, considering
+    /// [FORBIDDEN](StatusCode::FORBIDDEN) errors as not allowed.
+    fn authorize_many(
+        &self,
+        subject: &S,
+        relation: &str,
+        objects: &[&str],
+    ) -> impl Future<Output = Result<Vec<bool>>> + Send {
+        async move {
+            let mut ret = Vec::with_capacity(objects.len());
+            for object in objects {
+                match self.authorize(subject, relation, object).await {
+                    Ok(()) => ret.push(true),
+                    Err(err) if err.info().status() == StatusCode::FORBIDDEN => ret.push(false),
+                    Err(err) => return Err(err),
+                }
+            }
+            Ok(ret)
+        }
+    }
+
+    /// Retrieves every object of the given type the _subject_ is allowed to perform the _relation_ on.
+    ///
+    /// Objects can't be enumerated from [authorize](AuthorizationService::authorize) alone, so by default it fails
+    /// with an internal error.
+    fn list_objects(
+        &self,
+        _subject: &S,
+        relation: &str,
+        object_type: &str,
+    ) -> impl Future<Output = Result<Vec<String>>> + Send {
+        let err = Error::internal(format!(
+            "The authorization service can't list '{object_type}' objects with '{relation}' relation"
+        ));
+        std::future::ready(Err(err))
+    }
 }
 
 /// Trait implemented by the application State to provide specific auth service types.
@@ -44,3 +167,8 @@ pub trait AuthState<S: Subject>: Send + Sync + 'static {
     /// Retrieves the authorization service
     fn authz(&self) -> &Self::Authz;
 }
+
+/// Builds the `Set-Cookie` header value expiring the given cookie
+pub(crate) fn expired_cookie(name: &str) -> String {
+    format!("{name}=invalid; Expires=Thu, 01 Jan 1970 00:00:00 GMT")
+}
@@ -0,0 +1,199 @@
+use std::{
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use axum::{
+    extract::{rejection::RawPathParamsRejection, FromRequestParts, RawPathParams},
+    response::{IntoResponse, Response},
+};
+use http::{request::Parts, Request};
+use tower::{Layer, Service};
+
+use super::{client_value, fill_template, Auth, AuthState, AuthzCache, Subject};
+use crate::error::{ApiError, Error, GenericErrorCode, Result};
+
+/// Layer to require the authenticated subject to have a relation with an object, before calling the inner service.
+///
+/// The object is a template whose `{name}` placeholders are filled with the path params of the request, so it must be
+/// added with [route_layer](axum::Router::route_layer) for them to be available. The subject is extracted with the
+/// [Auth] extractor and the decisions are cached on the request [AuthzCache].
+///
+/// Requests are rejected with an [ApiError], either `AUTH_MISSING` if the subject is not authenticated or
+/// `AUTH_FAILED` if it doesn't have the relation.
+///
+/// ``` rust ignore
+/// let router = Router::new()
+///     .route("/documents/{id}", get(get_document).put(update_document))
+///     .route_layer(RequireAuthz::new(state.clone(), "read", "document:{id}"))
+///     .with_state(state);
+/// ```
+pub struct RequireAuthz<S, St> {
+    state: St,
+    relation: &'static str,
+    object: &'static str,
+    sub_type: PhantomData<fn() -> S>,
+}
+
+impl<S, St: Clone> Clone for RequireAuthz<S, St> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+            relation: self.relation,
+            object: self.object,
+            sub_type: PhantomData,
+        }
+    }
+}
+
+impl<S: Subject, St: AuthState<S> + Clone> RequireAuthz<S, St> {
+    /// Creates a new layer requiring the relation on the object template
+    pub fn new(state: St, relation: &'static str, object: &'static str) -> Self {
+        Self {
+            state,
+            relation,
+            object,
+            sub_type: PhantomData,
+        }
+    }
+}
+
+impl<S: Subject, St: AuthState<S> + Clone, I> Layer<I> for RequireAuthz<S, St> {
+    type Service = RequireAuthzService<S, St, I>;
+
+    fn layer(&self, inner: I) -> Self::Service {
+        RequireAuthzService {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Middleware to authorize requests.
+///
+/// See [RequireAuthz].
+pub struct RequireAuthzService<S, St, I> {
+    inner: I,
+    layer: RequireAuthz<S, St>,
+}
+
+impl<S, St: Clone, I: Clone> Clone for RequireAuthzService<S, St, I> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+impl<S, St, I, B> Service<Request<B>> for RequireAuthzService<S, St, I>
+where
+    S: Subject,
+    St: AuthState<S> + Clone,
+    I: Service<Request<B>, Response = Response> + Clone + Send + 'static,
+    I::Future: Send,
+    B: Send + 'static,
+{
+    type Error = I::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+    type Response = Response;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        // Take the service that was ready, leaving a clone in its place
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let layer = self.layer.clone();
+        Box::pin(async move {
+            let (mut parts, body) = req.into_parts();
+            if let Err(err) = layer.authorize(&mut parts).await {
+                return Ok(err.into_response());
+            }
+            inner.call(Request::from_parts(parts, body)).await
+        })
+    }
+}
+
+impl<S: Subject, St: AuthState<S>> RequireAuthz<S, St> {
+    async fn authorize(&self, parts: &mut Parts) -> Result<(), Box<ApiError>> {
+        let Auth(subject) = Auth::<S>::from_request_parts(parts, &self.state).await?;
+        let params = match RawPathParams::from_request_parts(parts, &self.state).await {
+            Ok(params) => Some(params),
+            // Placeholders will fail to be filled if there are no path params
+            Err(RawPathParamsRejection::MissingPathParams(_)) => None,
+            Err(rejection) => {
+                return Err(Error::new(GenericErrorCode::BadRequest)
+                    .with_reason("Invalid path params")
+                    .with_error_source(rejection)
+                    .into());
+            }
+        };
+        let object = fill_template(self.object, |name| {
+            params
+                .as_ref()
+                .and_then(|p| p.iter().find(|(n, _)| *n == name))
+                .map(|(_, v)| client_value(name, v))
+                .transpose()
+        })?;
+        let cache = parts.extensions.get_or_insert_default::<AuthzCache>().clone();
+        cache
+            .authorize(self.state.authz(), &subject, self.relation, &object)
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, routing::get, Router};
+    use http::StatusCode;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::auth::{
+        test_support::{TestAuthn, TestState, TestSubject},
+        InMemoryRelationStore, RebacAuthorizationService, RebacSchema,
+    };
+
+    #[tokio::test]
+    async fn test_require_authz() {
+        let schema = RebacSchema::parse("type user {}\ntype doc {\n relation viewer: user\n}").unwrap();
+        let authz = RebacAuthorizationService::new(schema, InMemoryRelationStore::new());
+        authz.write(&["doc:1#viewer@user:1".parse().unwrap()]).await.unwrap();
+        let authn = TestAuthn::default();
+        let state = TestState(authn.clone(), authz);
+
+        let router = Router::new()
+            .route("/docs/{id}", get(|Auth(subject): Auth<TestSubject>| async move { subject.to_string() }))
+            .route_layer(RequireAuthz::new(state.clone(), "viewer", "doc:{id}"))
+            .with_state(state);
+        let request = |user: Option<&str>, doc: &str| {
+            let mut req = Request::get(format!("/docs/{doc}"));
+            if let Some(user) = user {
+                req = req.header("x-user", user);
+            }
+            req.body(Body::empty()).unwrap()
+        };
+
+        let res = router.clone().oneshot(request(Some("1"), "1")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        // The handler reuses the subject authenticated by the layer
+        assert_eq!(authn.calls(), 1);
+
+        let res = router.clone().oneshot(request(Some("1"), "%FF")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let res = router.clone().oneshot(request(Some("1"), "1%23viewer%40user:1")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let res = router.clone().oneshot(request(Some("2"), "1")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let res = router.oneshot(request(None, "1")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -3,5 +3,25 @@
 crate::using! {
     pub error,
     pub interfaces,
+    pub credentials,
+    pub composite,
+    pub rebac,
+    pub cache,
+    pub(crate) template,
     pub extractor,
+    pub response,
+    pub layer,
+    pub scope,
 }
+
+#[cfg(feature = "api-key")]
+crate::using!(pub api_key);
+
+#[cfg(feature = "jwt")]
+crate::using!(pub jwt);
+
+#[cfg(feature = "session")]
+crate::using!(pub session);
+
+#[cfg(test)]
+pub(crate) mod test_support;
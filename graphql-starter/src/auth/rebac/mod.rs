@@ -0,0 +1,8 @@
+//! Relationship-based authorization, modeled after Zanzibar
+
+crate::using! {
+    pub tuple,
+    pub schema,
+    pub store,
+    pub service,
+}
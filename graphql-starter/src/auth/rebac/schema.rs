@@ -0,0 +1,314 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::Context;
+
+use super::{RelationTuple, SubjectRef};
+
+/// Schema of the object types, their relations and how they're computed.
+///
+/// Every type is defined with its relations, which can include the types of subjects allowed on tuples and a rewrite
+/// expression, the union (`|`) of other relations of the same object (`editor`) or relations of the objects related
+/// through another relation (`parent->viewer`):
+///
+/// ``` text
+/// // Comments start with a double slash
+/// type user {}
+///
+/// type group {
+///     relation member: user | group#member
+/// }
+///
+/// type folder {
+///     relation owner: user
+///     relation viewer: user | group#member = owner
+/// }
+///
+/// type doc {
+///     relation parent: folder
+///     relation owner: user
+///     relation editor: user | group#member = owner
+///     relation viewer: user | group#member = editor | parent->viewer
+/// }
+/// ```
+///
+/// Relations without allowed types can't be written directly and are only computed from the rewrite, the `permission`
+/// keyword can be used instead of `relation` for those.
+#[derive(Debug, Clone, Default)]
+pub struct RebacSchema {
+    types: HashMap<String, TypeDefinition>,
+}
+
+/// Definition of an object type
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TypeDefinition {
+    pub(crate) relations: HashMap<String, RelationDefinition>,
+}
+
+/// Definition of a relation
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RelationDefinition {
+    /// Subjects allowed on tuples, if empty the relation can't be written directly
+    pub(crate) allowed: Vec<AllowedSubject>,
+    /// Other relations implying this one
+    pub(crate) rewrite: Vec<RewriteTerm>,
+}
+
+/// Subject allowed on a relation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum AllowedSubject {
+    /// Objects of the type, like `user`
+    Type(String),
+    /// Usersets of the type and relation, like `group#member`
+    Userset(String, String),
+}
+
+/// Term of a relation rewrite
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum RewriteTerm {
+    /// Another relation of the same object, like `editor`
+    Computed(String),
+    /// The relation of the objects related through the tupleset relation, like `parent->viewer`
+    TupleToUserset { tupleset: String, computed: String },
+}
+
+impl RebacSchema {
+    /// Parses the schema
+    pub fn parse(schema: &str) -> anyhow::Result<Self> {
+        let mut ret = Self::default();
+        let mut current: Option<(String, TypeDefinition)> = None;
+        for (idx, line) in schema.lines().enumerate() {
+            let line = line.split_once("//").map(|(l, _)| l).unwrap_or(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            let ctx = || format!("Invalid schema at line {}: {line}", idx + 1);
+
+            if let Some(definition) = line.strip_prefix("type ") {
+                anyhow::ensure!(current.is_none(), "{}, missing closing brace", ctx());
+                let (name, rest) = definition.split_once('{').with_context(ctx)?;
+                let name = ident(name.trim()).with_context(ctx)?;
+                anyhow::ensure!(!ret.types.contains_key(name), "{}, duplicated type", ctx());
+                match rest.trim() {
+                    "}" => {
+                        ret.types.insert(name.to_owned(), TypeDefinition::default());
+                    }
+                    "" => current = Some((name.to_owned(), TypeDefinition::default())),
+                    _ => anyhow::bail!("{}, relations must be defined on their own line", ctx()),
+                }
+            } else if line == "}" {
+                let (name, definition) = current.take().with_context(ctx)?;
+                ret.types.insert(name, definition);
+            } else if let Some((_, definition)) = &mut current {
+                let (name, relation) = parse_relation(line).with_context(ctx)?;
+                anyhow::ensure!(
+                    !definition.relations.contains_key(&name),
+                    "{}, duplicated relation",
+                    ctx()
+                );
+                definition.relations.insert(name, relation);
+            } else {
+                anyhow::bail!("{}, expected a type definition", ctx());
+            }
+        }
+        anyhow::ensure!(current.is_none(), "Invalid schema, missing closing brace");
+
+        ret.validate()?;
+        Ok(ret)
+    }
+
+    /// Reads the schema from a file
+    pub fn from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let schema = std::fs::read_to_string(path).with_context(|| format!("Couldn't read {}", path.display()))?;
+        Self::parse(&schema).with_context(|| format!("Couldn't parse {}", path.display()))
+    }
+
+    /// Checks whether the type is defined
+    pub fn has_type(&self, object_type: &str) -> bool {
+        self.types.contains_key(object_type)
+    }
+
+    /// Checks whether the relation is defined for the type
+    pub fn has_relation(&self, object_type: &str, relation: &str) -> bool {
+        self.relation(object_type, relation).is_some()
+    }
+
+    /// Validates that the tuple can be written, based on the allowed subjects of the relation
+    pub fn validate_tuple(&self, tuple: &RelationTuple) -> anyhow::Result<()> {
+        let relation = self
+            .relation(&tuple.object.object_type, &tuple.relation)
+            .with_context(|| format!("Unknown relation on tuple '{tuple}'"))?;
+        let allowed = relation.allowed.iter().any(|allowed| match (allowed, &tuple.subject) {
+            (AllowedSubject::Type(t), SubjectRef::Object(o)) => *t == o.object_type,
+            (AllowedSubject::Userset(t, r), SubjectRef::Userset(o, rel)) => *t == o.object_type && r == rel,
+            _ => false,
+        });
+        anyhow::ensure!(allowed, "The subject is not allowed on tuple '{tuple}'");
+        Ok(())
+    }
+
+    pub(crate) fn relation(&self, object_type: &str, relation: &str) -> Option<&RelationDefinition> {
+        self.types.get(object_type)?.relations.get(relation)
+    }
+
+    /// Validates that every reference is defined
+    fn validate(&self) -> anyhow::Result<()> {
+        for (type_name, definition) in &self.types {
+            for (relation_name, relation) in &definition.relations {
+                let ctx = || format!("Invalid relation '{type_name}#{relation_name}'");
+                for allowed in &relation.allowed {
+                    match allowed {
+                        AllowedSubject::Type(t) => {
+                            anyhow::ensure!(self.has_type(t), "{}, unknown type '{t}'", ctx())
+                        }
+                        AllowedSubject::Userset(t, r) => {
+                            anyhow::ensure!(self.has_relation(t, r), "{}, unknown relation '{t}#{r}'", ctx())
+                        }
+                    }
+                }
+                for term in &relation.rewrite {
+                    match term {
+                        RewriteTerm::Computed(r) => {
+                            anyhow::ensure!(
+                                definition.relations.contains_key(r),
+                                "{}, unknown relation '{r}'",
+                                ctx()
+                            )
+                        }
+                        RewriteTerm::TupleToUserset { tupleset, computed } => {
+                            let tupleset_relation = definition
+                                .relations
+                                .get(tupleset)
+                                .with_context(|| format!("{}, unknown relation '{tupleset}'", ctx()))?;
+                            for allowed in &tupleset_relation.allowed {
+                                let AllowedSubject::Type(t) = allowed else {
+                                    anyhow::bail!("{}, '{tupleset}' can't allow usersets", ctx());
+                                };
+                                anyhow::ensure!(
+                                    self.has_relation(t, computed),
+                                    "{}, unknown relation '{t}#{computed}'",
+                                    ctx()
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses a relation line, like `relation viewer: user | group#member = editor | parent->viewer`
+fn parse_relation(line: &str) -> anyhow::Result<(String, RelationDefinition)> {
+    let (keyword, rest) = line.split_once(char::is_whitespace).context("Expected a relation")?;
+    anyhow::ensure!(
+        keyword == "relation" || keyword == "permission",
+        "Expected 'relation' or 'permission'"
+    );
+    let (definition, rewrite) = match rest.split_once('=') {
+        Some((definition, rewrite)) => (definition, Some(rewrite)),
+        None => (rest, None),
+    };
+    let (name, allowed) = match definition.split_once(':') {
+        Some((name, allowed)) => (name, Some(allowed)),
+        None => (definition, None),
+    };
+    let name = ident(name.trim())?;
+
+    let mut ret = RelationDefinition::default();
+    if let Some(allowed) = allowed {
+        for subject in allowed.split('|').map(str::trim) {
+            ret.allowed.push(match subject.split_once('#') {
+                Some((t, r)) => AllowedSubject::Userset(ident(t)?.to_owned(), ident(r)?.to_owned()),
+                None => AllowedSubject::Type(ident(subject)?.to_owned()),
+            });
+        }
+    }
+    if let Some(rewrite) = rewrite {
+        for term in rewrite.split('|').map(str::trim) {
+            ret.rewrite.push(match term.split_once("->") {
+                Some((tupleset, computed)) => RewriteTerm::TupleToUserset {
+                    tupleset: ident(tupleset.trim())?.to_owned(),
+                    computed: ident(computed.trim())?.to_owned(),
+                },
+                None => RewriteTerm::Computed(ident(term)?.to_owned()),
+            });
+        }
+    }
+    anyhow::ensure!(
+        keyword == "relation" || ret.allowed.is_empty(),
+        "Permissions can't allow subjects"
+    );
+    anyhow::ensure!(
+        !ret.allowed.is_empty() || !ret.rewrite.is_empty(),
+        "The relation must allow subjects or have a rewrite"
+    );
+    Ok((name.to_owned(), ret))
+}
+
+/// Validates an identifier
+fn ident(value: &str) -> anyhow::Result<&str> {
+    anyhow::ensure!(
+        !value.is_empty() && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-'),
+        "Invalid identifier '{value}'"
+    );
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_schema() {
+        let schema = RebacSchema::parse(
+            r#"
+            type user {}
+            type group {
+                relation member: user | group#member
+            }
+            type doc {
+                relation parent: doc
+                relation owner: user // the creator
+                relation viewer: user | group#member = owner | parent->viewer
+                permission share = owner
+            }
+            "#,
+        )
+        .unwrap();
+        let viewer = schema.relation("doc", "viewer").unwrap();
+        assert_eq!(
+            viewer.allowed,
+            [
+                AllowedSubject::Type("user".into()),
+                AllowedSubject::Userset("group".into(), "member".into())
+            ]
+        );
+        assert_eq!(
+            viewer.rewrite,
+            [
+                RewriteTerm::Computed("owner".into()),
+                RewriteTerm::TupleToUserset {
+                    tupleset: "parent".into(),
+                    computed: "viewer".into()
+                }
+            ]
+        );
+        assert!(schema.has_relation("doc", "share"));
+        assert!(schema.validate_tuple(&"doc:1#viewer@group:eng#member".parse().unwrap()).is_ok());
+        assert!(schema.validate_tuple(&"doc:1#owner@group:eng#member".parse().unwrap()).is_err());
+        assert!(schema.validate_tuple(&"doc:1#share@user:1".parse().unwrap()).is_err());
+
+        let invalid = [
+            "type doc { relation owner: user }",
+            "type doc {\n relation owner: user\n}",
+            "type doc {\n relation viewer = owner\n}",
+            "type user {}\ntype doc {\n permission owner: user\n}",
+            "type user {}\ntype doc {\n relation parent: user\n relation viewer = parent->viewer\n}",
+        ];
+        for schema in invalid {
+            assert!(RebacSchema::parse(schema).is_err(), "{schema}");
+        }
+    }
+}
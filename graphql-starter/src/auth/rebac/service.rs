@@ -0,0 +1,349 @@
+use std::{collections::HashSet, future::Future, pin::Pin, sync::Arc};
+
+use futures_util::{stream, StreamExt, TryStreamExt};
+
+use super::{ObjectRef, RebacSchema, RelationStore, RelationTuple, RewriteTerm, SubjectRef};
+use crate::{
+    auth::{AuthErrorCode, AuthorizationService, Subject},
+    error::{Error, GenericErrorCode, Result},
+};
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Relationship-based [AuthorizationService], checking relations between objects and subjects based on the stored
+/// [tuples](RelationTuple) and the [schema](RebacSchema).
+///
+/// A subject has a relation with an object if there's a tuple for it, if it's a member of a userset having the relation
+/// or if any of the relation rewrites does (recursively).
+///
+/// When used as an [AuthorizationService], the [Subject] display must be its object reference, like `user:1`, and the
+/// object must be also an object reference, like `doc:1`.
+///
+/// ``` rust ignore
+/// let schema = RebacSchema::from_file("authz.schema")?;
+/// let authz = RebacAuthorizationService::new(schema, InMemoryRelationStore::new());
+/// authz.write(&["doc:1#owner@user:1".parse()?]).await?;
+/// assert!(authz.check(&"doc:1".parse()?, "viewer", &"user:1".parse()?).await?);
+/// ```
+pub struct RebacAuthorizationService<St> {
+    schema: Arc<RebacSchema>,
+    store: St,
+    max_depth: usize,
+    max_concurrency: usize,
+}
+
+impl<St: Clone> Clone for RebacAuthorizationService<St> {
+    fn clone(&self) -> Self {
+        Self {
+            schema: self.schema.clone(),
+            store: self.store.clone(),
+            max_depth: self.max_depth,
+            max_concurrency: self.max_concurrency,
+        }
+    }
+}
+
+impl<St: RelationStore> RebacAuthorizationService<St> {
+    /// Creates a new service with the given schema and store
+    pub fn new(schema: RebacSchema, store: St) -> Self {
+        Self {
+            schema: Arc::new(schema),
+            store,
+            max_depth: 25,
+            max_concurrency: 16,
+        }
+    }
+
+    /// Modifies the maximum depth of nested relations to traverse when checking, 25 by default
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Modifies the maximum number of objects checked concurrently when authorizing or listing many of them, 16 by
+    /// default
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = max_concurrency.max(1);
+        self
+    }
+
+    /// Retrieves the schema
+    pub fn schema(&self) -> &RebacSchema {
+        &self.schema
+    }
+
+    /// Retrieves the relation store
+    pub fn store(&self) -> &St {
+        &self.store
+    }
+
+    /// Writes the tuples, after validating them against the schema
+    pub async fn write(&self, tuples: &[RelationTuple]) -> Result<()> {
+        self.validate(tuples)?;
+        self.store.write(tuples).await
+    }
+
+    /// Deletes the tuples
+    pub async fn delete(&self, tuples: &[RelationTuple]) -> Result<()> {
+        self.store.delete(tuples).await
+    }
+
+    /// Checks whether the subject has the relation with the object
+    pub async fn check(&self, object: &ObjectRef, relation: &str, subject: &SubjectRef) -> Result<bool> {
+        if !self.schema.has_relation(&object.object_type, relation) {
+            return Err(Error::internal(format!(
+                "Unknown relation '{relation}' for type '{}'",
+                object.object_type
+            )));
+        }
+        self.check_relation(object, relation, subject, &mut HashSet::new(), 0).await
+    }
+
+    /// Checks whether the subject has the relation with each one of the objects, bounding the concurrent checks
+    fn check_many<'a>(
+        &'a self,
+        objects: &'a [ObjectRef],
+        relation: &'a str,
+        subject: &'a SubjectRef,
+    ) -> BoxFuture<'a, Result<Vec<bool>>> {
+        Box::pin(
+            stream::iter(objects)
+                .map(move |o| self.check(o, relation, subject))
+                .buffered(self.max_concurrency)
+                .try_collect(),
+        )
+    }
+
+    fn validate(&self, tuples: &[RelationTuple]) -> Result<()> {
+        for tuple in tuples {
+            self.schema.validate_tuple(tuple).map_err(|err| {
+                Error::new(GenericErrorCode::BadRequest)
+                    .with_reason(err.to_string())
+                    .with_error_source(err)
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Checks the relation, keeping track of the relations visited by any branch of the check.
+    ///
+    /// As the check finishes as soon as any branch is allowed, a visited relation is either being checked (a cycle) or
+    /// already denied, so it's never checked twice.
+    fn check_relation<'a>(
+        &'a self,
+        object: &'a ObjectRef,
+        relation: &'a str,
+        subject: &'a SubjectRef,
+        visited: &'a mut HashSet<(ObjectRef, String)>,
+        depth: usize,
+    ) -> BoxFuture<'a, Result<bool>> {
+        Box::pin(async move {
+            // The subject might be the userset itself
+            if let SubjectRef::Userset(o, r) = subject
+                && o == object
+                && r == relation
+            {
+                return Ok(true);
+            }
+            let Some(definition) = self.schema.relation(&object.object_type, relation) else {
+                return Ok(false);
+            };
+            let key = (object.clone(), relation.to_owned());
+            if visited.contains(&key) {
+                return Ok(false);
+            }
+            if depth >= self.max_depth {
+                return Err(Error::internal(format!(
+                    "Exceeded the maximum depth checking '{object}#{relation}@{subject}'"
+                )));
+            }
+            visited.insert(key);
+
+            // Check the direct tuples and usersets
+            if !definition.allowed.is_empty() {
+                let subjects = self.store.read(object, relation).await?;
+                if subjects.contains(subject) {
+                    return Ok(true);
+                }
+                for s in &subjects {
+                    if let SubjectRef::Userset(o, r) = s
+                        && self.check_relation(o, r, subject, visited, depth + 1).await?
+                    {
+                        return Ok(true);
+                    }
+                }
+            }
+
+            // Check the rewrites
+            for term in &definition.rewrite {
+                match term {
+                    RewriteTerm::Computed(computed) => {
+                        if self.check_relation(object, computed, subject, visited, depth + 1).await? {
+                            return Ok(true);
+                        }
+                    }
+                    RewriteTerm::TupleToUserset { tupleset, computed } => {
+                        for s in self.store.read(object, tupleset).await? {
+                            if let SubjectRef::Object(o) = &s
+                                && self.check_relation(o, computed, subject, visited, depth + 1).await?
+                            {
+                                return Ok(true);
+                            }
+                        }
+                    }
+                }
+            }
+
+            Ok(false)
+        })
+    }
+}
+
+impl<S: Subject, St: RelationStore> AuthorizationService<S> for RebacAuthorizationService<St> {
+    async fn authorize(&self, subject: &S, relation: &str, object: &str) -> Result<()> {
+        let subject_ref = subject_ref(subject)?;
+        if self.check(&object_ref(object)?, relation, &subject_ref).await? {
+            Ok(())
+        } else {
+            Err(Error::new(AuthErrorCode::AuthFailed)
+                .with_reason(format!("'{subject}' doesn't have '{relation}' relation with '{object}'")))
+        }
+    }
+
+    async fn authorize_many(&self, subject: &S, relation: &str, objects: &[&str]) -> Result<Vec<bool>> {
+        let subject = subject_ref(subject)?;
+        let objects = objects.iter().map(|o| object_ref(o)).collect::<Result<Vec<_>>>()?;
+        self.check_many(&objects, relation, &subject).await
+    }
+
+    async fn list_objects(&self, subject: &S, relation: &str, object_type: &str) -> Result<Vec<String>> {
+        let subject = subject_ref(subject)?;
+        if !self.schema.has_relation(object_type, relation) {
+            return Err(Error::internal(format!(
+                "Unknown relation '{relation}' for type '{object_type}'"
+            )));
+        }
+        let objects = self.store.objects(object_type).await?;
+        let allowed = self.check_many(&objects, relation, &subject).await?;
+        Ok(objects
+            .into_iter()
+            .zip(allowed)
+            .filter(|(_, allowed)| *allowed)
+            .map(|(o, _)| o.to_string())
+            .collect())
+    }
+}
+
+/// Parses the subject display as an object reference
+fn subject_ref(subject: &impl Subject) -> Result<SubjectRef> {
+    let subject = subject.to_string();
+    let subject_ref = subject
+        .parse::<ObjectRef>()
+        .map_err(|err| Error::internal(format!("Invalid subject '{subject}'")).with_error_source(err))?;
+    Ok(subject_ref.into())
+}
+
+/// Parses an object reference
+fn object_ref(object: &str) -> Result<ObjectRef> {
+    object
+        .parse()
+        .map_err(|err: anyhow::Error| Error::internal(format!("Invalid object '{object}'")).with_error_source(err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{test_support::TestSubject, InMemoryRelationStore};
+
+    const SCHEMA: &str = r#"
+        type user {}
+        type group {
+            relation member: user | group#member
+        }
+        type folder {
+            relation owner: user
+            relation viewer: user | group#member = owner
+        }
+        type doc {
+            relation parent: folder
+            relation owner: user
+            relation editor: user | group#member = owner
+            relation viewer: user | group#member = editor | parent->viewer
+        }
+    "#;
+
+    #[tokio::test]
+    async fn test_rebac_authorization() {
+        let authz = RebacAuthorizationService::new(RebacSchema::parse(SCHEMA).unwrap(), InMemoryRelationStore::new())
+            .with_max_concurrency(2);
+        let tuples = [
+            "doc:1#owner@user:owner",
+            "doc:1#editor@group:eng#member",
+            "group:eng#member@group:backend#member",
+            "group:backend#member@user:dev",
+            "group:backend#member@group:eng#member",
+            "doc:1#parent@folder:shared",
+            "folder:shared#owner@user:boss",
+        ]
+        .map(|t| t.parse::<RelationTuple>().unwrap());
+        authz.write(&tuples).await.unwrap();
+
+        let allowed = [
+            ("user:owner", "owner"),
+            ("user:owner", "editor"),
+            ("user:owner", "viewer"),
+            ("user:dev", "editor"),
+            ("user:dev", "viewer"),
+            ("user:boss", "viewer"),
+        ];
+        for (subject, relation) in allowed {
+            authz
+                .authorize(&TestSubject::new(subject), relation, "doc:1")
+                .await
+                .unwrap_or_else(|err| panic!("{subject} {relation}: {err}"));
+        }
+
+        let denied = [("user:dev", "owner"), ("user:boss", "editor"), ("user:other", "viewer")];
+        for (subject, relation) in denied {
+            let err = authz
+                .authorize(&TestSubject::new(subject), relation, "doc:1")
+                .await
+                .unwrap_err();
+            assert_eq!(err.info().code(), "AUTH_FAILED");
+        }
+
+        // Usersets can be checked as well
+        let userset = "group:backend#member".parse().unwrap();
+        assert!(authz.check(&"doc:1".parse().unwrap(), "viewer", &userset).await.unwrap());
+
+        // Deleted tuples are no longer considered
+        authz.delete(&tuples[3..4]).await.unwrap();
+        assert!(
+            authz
+                .authorize(&TestSubject::new("user:dev"), "viewer", "doc:1")
+                .await
+                .is_err()
+        );
+
+        // Many objects can be authorized at once
+        authz.write(&["doc:2#viewer@user:other".parse().unwrap()]).await.unwrap();
+        let subject = TestSubject::new("user:owner");
+        let allowed = authz
+            .authorize_many(&subject, "editor", &["doc:1", "doc:2", "doc:3"])
+            .await
+            .unwrap();
+        assert_eq!(allowed, [true, false, false]);
+        let objects = authz.list_objects(&subject, "viewer", "doc").await.unwrap();
+        assert_eq!(objects, ["doc:1"]);
+        let objects = authz
+            .list_objects(&TestSubject::new("user:other"), "viewer", "doc")
+            .await
+            .unwrap();
+        assert_eq!(objects, ["doc:2"]);
+
+        // Invalid tuples can't be written
+        let invalid = "doc:1#owner@group:eng#member".parse().unwrap();
+        let err = authz.write(&[invalid]).await.unwrap_err();
+        assert_eq!(err.info().code(), "BAD_REQUEST");
+    }
+}
@@ -0,0 +1,243 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{Arc, RwLock},
+};
+
+use auto_impl::auto_impl;
+
+use super::{ObjectRef, RelationTuple, SubjectRef};
+use crate::error::Result;
+
+/// Storage of relation tuples
+#[auto_impl(Box, Arc)]
+#[trait_variant::make(Send)]
+pub trait RelationStore: Send + Sync + Sized + Clone + 'static {
+    /// Writes the tuples, ignoring the ones already existing
+    async fn write(&self, tuples: &[RelationTuple]) -> Result<()>;
+
+    /// Deletes the tuples, ignoring the ones not existing
+    async fn delete(&self, tuples: &[RelationTuple]) -> Result<()>;
+
+    /// Retrieves the subjects having the relation with the object
+    async fn read(&self, object: &ObjectRef, relation: &str) -> Result<Vec<SubjectRef>>;
+
+    /// Retrieves every object of the given type having at least one tuple
+    async fn objects(&self, object_type: &str) -> Result<Vec<ObjectRef>>;
+}
+
+/// [RelationStore] keeping the tuples in memory.
+///
+/// It can be cheaply cloned, sharing the same tuples.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryRelationStore {
+    tuples: Arc<RwLock<HashMap<(ObjectRef, String), BTreeSet<SubjectRef>>>>,
+}
+
+impl InMemoryRelationStore {
+    /// Creates a new empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl RelationStore for InMemoryRelationStore {
+    async fn write(&self, tuples: &[RelationTuple]) -> Result<()> {
+        let mut store = self.tuples.write().unwrap_or_else(|e| e.into_inner());
+        for tuple in tuples {
+            store
+                .entry((tuple.object.clone(), tuple.relation.clone()))
+                .or_default()
+                .insert(tuple.subject.clone());
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, tuples: &[RelationTuple]) -> Result<()> {
+        let mut store = self.tuples.write().unwrap_or_else(|e| e.into_inner());
+        for tuple in tuples {
+            let key = (tuple.object.clone(), tuple.relation.clone());
+            if let Some(subjects) = store.get_mut(&key) {
+                subjects.remove(&tuple.subject);
+                if subjects.is_empty() {
+                    store.remove(&key);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn read(&self, object: &ObjectRef, relation: &str) -> Result<Vec<SubjectRef>> {
+        let store = self.tuples.read().unwrap_or_else(|e| e.into_inner());
+        Ok(store
+            .get(&(object.clone(), relation.to_owned()))
+            .map(|subjects| subjects.iter().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    async fn objects(&self, object_type: &str) -> Result<Vec<ObjectRef>> {
+        let store = self.tuples.read().unwrap_or_else(|e| e.into_inner());
+        let objects = store
+            .keys()
+            .filter(|(object, _)| object.object_type == object_type)
+            .map(|(object, _)| object.clone())
+            .collect::<BTreeSet<_>>();
+        Ok(objects.into_iter().collect())
+    }
+}
+
+#[cfg(feature = "postgres")]
+pub use self::postgres::PgRelationStore;
+
+#[cfg(feature = "postgres")]
+mod postgres {
+    use sqlx::PgPool;
+
+    use super::{ObjectRef, RelationStore, RelationTuple, SubjectRef};
+    use crate::{error::Result, sqlx::MapSqlxErr};
+
+    /// [RelationStore] backed by a Postgres table, `relation_tuples` by default.
+    ///
+    /// The table can be created with [create_table](PgRelationStore::create_table) or with a migration like:
+    ///
+    /// ``` sql
+    /// CREATE TABLE "relation_tuples" (
+    ///     "object_type" VARCHAR(64) NOT NULL,
+    ///     "object_id" VARCHAR(255) NOT NULL,
+    ///     "relation" VARCHAR(64) NOT NULL,
+    ///     "subject_type" VARCHAR(64) NOT NULL,
+    ///     "subject_id" VARCHAR(255) NOT NULL,
+    ///     "subject_relation" VARCHAR(64) NOT NULL DEFAULT '',
+    ///     PRIMARY KEY ("object_type", "object_id", "relation", "subject_type", "subject_id", "subject_relation")
+    /// );
+    /// ```
+    #[derive(Debug, Clone)]
+    pub struct PgRelationStore {
+        pool: PgPool,
+        table: String,
+    }
+
+    impl PgRelationStore {
+        /// Creates a new store on the `relation_tuples` table
+        pub fn new(pool: PgPool) -> Self {
+            Self {
+                pool,
+                table: "relation_tuples".into(),
+            }
+        }
+
+        /// Modifies the table name
+        pub fn with_table(mut self, table: impl Into<String>) -> Self {
+            self.table = table.into();
+            self
+        }
+
+        /// Creates the table, if it doesn't exist yet
+        pub async fn create_table(&self) -> Result<()> {
+            sqlx::raw_sql(&format!(
+                r#"
+                CREATE TABLE IF NOT EXISTS "{}" (
+                    "object_type" VARCHAR(64) NOT NULL,
+                    "object_id" VARCHAR(255) NOT NULL,
+                    "relation" VARCHAR(64) NOT NULL,
+                    "subject_type" VARCHAR(64) NOT NULL,
+                    "subject_id" VARCHAR(255) NOT NULL,
+                    "subject_relation" VARCHAR(64) NOT NULL DEFAULT '',
+                    PRIMARY KEY ("object_type", "object_id", "relation", "subject_type", "subject_id", "subject_relation")
+                );
+                "#,
+                self.table
+            ))
+            .execute(&self.pool)
+            .await
+            .map_sqlx_err("Couldn't create the relation tuples table")?;
+            Ok(())
+        }
+    }
+
+    impl RelationStore for PgRelationStore {
+        async fn write(&self, tuples: &[RelationTuple]) -> Result<()> {
+            let query = format!(
+                r#"INSERT INTO "{}" ("object_type", "object_id", "relation", "subject_type", "subject_id", "subject_relation")
+                VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT DO NOTHING"#,
+                self.table
+            );
+            let mut tx = self.pool.begin().await.map_sqlx_err("Couldn't begin a transaction")?;
+            for tuple in tuples {
+                let subject = tuple.subject.object();
+                sqlx::query(&query)
+                    .bind(&tuple.object.object_type)
+                    .bind(&tuple.object.id)
+                    .bind(&tuple.relation)
+                    .bind(&subject.object_type)
+                    .bind(&subject.id)
+                    .bind(tuple.subject.relation().unwrap_or_default())
+                    .execute(&mut *tx)
+                    .await
+                    .map_sqlx_err("Couldn't write the relation tuple")?;
+            }
+            tx.commit().await.map_sqlx_err("Couldn't commit the transaction")?;
+            Ok(())
+        }
+
+        async fn delete(&self, tuples: &[RelationTuple]) -> Result<()> {
+            let query = format!(
+                r#"DELETE FROM "{}" WHERE "object_type" = $1 AND "object_id" = $2 AND "relation" = $3
+                AND "subject_type" = $4 AND "subject_id" = $5 AND "subject_relation" = $6"#,
+                self.table
+            );
+            let mut tx = self.pool.begin().await.map_sqlx_err("Couldn't begin a transaction")?;
+            for tuple in tuples {
+                let subject = tuple.subject.object();
+                sqlx::query(&query)
+                    .bind(&tuple.object.object_type)
+                    .bind(&tuple.object.id)
+                    .bind(&tuple.relation)
+                    .bind(&subject.object_type)
+                    .bind(&subject.id)
+                    .bind(tuple.subject.relation().unwrap_or_default())
+                    .execute(&mut *tx)
+                    .await
+                    .map_sqlx_err("Couldn't delete the relation tuple")?;
+            }
+            tx.commit().await.map_sqlx_err("Couldn't commit the transaction")?;
+            Ok(())
+        }
+
+        async fn read(&self, object: &ObjectRef, relation: &str) -> Result<Vec<SubjectRef>> {
+            let rows = sqlx::query_as::<_, (String, String, String)>(&format!(
+                r#"SELECT "subject_type", "subject_id", "subject_relation" FROM "{}"
+                WHERE "object_type" = $1 AND "object_id" = $2 AND "relation" = $3"#,
+                self.table
+            ))
+            .bind(&object.object_type)
+            .bind(&object.id)
+            .bind(relation)
+            .fetch_all(&self.pool)
+            .await
+            .map_sqlx_err("Couldn't read the relation tuples")?;
+            Ok(rows
+                .into_iter()
+                .map(|(subject_type, subject_id, subject_relation)| {
+                    let subject = ObjectRef::new(subject_type, subject_id);
+                    if subject_relation.is_empty() {
+                        SubjectRef::Object(subject)
+                    } else {
+                        SubjectRef::Userset(subject, subject_relation)
+                    }
+                })
+                .collect())
+        }
+
+        async fn objects(&self, object_type: &str) -> Result<Vec<ObjectRef>> {
+            let ids = sqlx::query_scalar::<_, String>(&format!(
+                r#"SELECT DISTINCT "object_id" FROM "{}" WHERE "object_type" = $1 ORDER BY "object_id""#,
+                self.table
+            ))
+            .bind(object_type)
+            .fetch_all(&self.pool)
+            .await
+            .map_sqlx_err("Couldn't read the relation tuples objects")?;
+            Ok(ids.into_iter().map(|id| ObjectRef::new(object_type, id)).collect())
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::Context;
+
+/// Reference to an object, like `doc:1`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ObjectRef {
+    /// The type of the object
+    pub object_type: String,
+    /// The id of the object
+    pub id: String,
+}
+
+impl ObjectRef {
+    /// Creates a new object reference
+    pub fn new(object_type: impl Into<String>, id: impl Into<String>) -> Self {
+        Self {
+            object_type: object_type.into(),
+            id: id.into(),
+        }
+    }
+}
+
+impl FromStr for ObjectRef {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (object_type, id) = s
+            .split_once(':')
+            .with_context(|| format!("Invalid object '{s}', expected 'type:id'"))?;
+        anyhow::ensure!(
+            !object_type.is_empty() && !id.is_empty() && !s.contains(['#', '@']),
+            "Invalid object '{s}', expected 'type:id'"
+        );
+        Ok(Self::new(object_type, id))
+    }
+}
+
+impl fmt::Display for ObjectRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.object_type, self.id)
+    }
+}
+
+/// Subject of a relation tuple, either an object (`user:1`) or a set of subjects having a relation with an object
+/// (`group:eng#member`)
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SubjectRef {
+    /// A concrete object
+    Object(ObjectRef),
+    /// Every subject having the relation with the object
+    Userset(ObjectRef, String),
+}
+
+impl SubjectRef {
+    /// Retrieves the object of the subject
+    pub fn object(&self) -> &ObjectRef {
+        match self {
+            SubjectRef::Object(object) | SubjectRef::Userset(object, _) => object,
+        }
+    }
+
+    /// Retrieves the relation of the userset (if any)
+    pub fn relation(&self) -> Option<&str> {
+        match self {
+            SubjectRef::Object(_) => None,
+            SubjectRef::Userset(_, relation) => Some(relation),
+        }
+    }
+}
+
+impl From<ObjectRef> for SubjectRef {
+    fn from(object: ObjectRef) -> Self {
+        SubjectRef::Object(object)
+    }
+}
+
+impl FromStr for SubjectRef {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('#') {
+            Some((object, relation)) if !relation.is_empty() => {
+                Ok(SubjectRef::Userset(object.parse()?, relation.to_owned()))
+            }
+            Some(_) => anyhow::bail!("Invalid subject '{s}', expected 'type:id' or 'type:id#relation'"),
+            None => Ok(SubjectRef::Object(s.parse()?)),
+        }
+    }
+}
+
+impl fmt::Display for SubjectRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubjectRef::Object(object) => write!(f, "{object}"),
+            SubjectRef::Userset(object, relation) => write!(f, "{object}#{relation}"),
+        }
+    }
+}
+
+/// Relation between an object and a subject, like `doc:1#viewer@user:2` or `doc:1#viewer@group:eng#member`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RelationTuple {
+    /// The object
+    pub object: ObjectRef,
+    /// The relation
+    pub relation: String,
+    /// The subject having the relation with the object
+    pub subject: SubjectRef,
+}
+
+impl RelationTuple {
+    /// Creates a new tuple
+    pub fn new(object: ObjectRef, relation: impl Into<String>, subject: impl Into<SubjectRef>) -> Self {
+        Self {
+            object,
+            relation: relation.into(),
+            subject: subject.into(),
+        }
+    }
+}
+
+impl FromStr for RelationTuple {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid tuple '{s}', expected 'type:id#relation@subject'");
+        let (object, subject) = s.split_once('@').with_context(invalid)?;
+        let (object, relation) = object.split_once('#').with_context(invalid)?;
+        anyhow::ensure!(!relation.is_empty(), invalid());
+        Ok(Self::new(
+            object.parse().with_context(invalid)?,
+            relation,
+            subject.parse::<SubjectRef>().with_context(invalid)?,
+        ))
+    }
+}
+
+impl fmt::Display for RelationTuple {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}#{}@{}", self.object, self.relation, self.subject)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tuples() {
+        let tuple: RelationTuple = "doc:1#viewer@user:2".parse().unwrap();
+        assert_eq!(tuple.object, ObjectRef::new("doc", "1"));
+        assert_eq!(tuple.relation, "viewer");
+        assert_eq!(tuple.subject, SubjectRef::Object(ObjectRef::new("user", "2")));
+
+        let tuple: RelationTuple = "doc:1#viewer@group:eng#member".parse().unwrap();
+        assert_eq!(
+            tuple.subject,
+            SubjectRef::Userset(ObjectRef::new("group", "eng"), "member".into())
+        );
+        assert_eq!(tuple.to_string(), "doc:1#viewer@group:eng#member");
+
+        for invalid in ["doc:1#viewer", "doc:1@user:2", "doc#viewer@user:2", "doc:1#@user:2", "doc:1#viewer@user"] {
+            assert!(invalid.parse::<RelationTuple>().is_err(), "{invalid}");
+        }
+    }
+}
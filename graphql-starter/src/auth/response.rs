@@ -0,0 +1,167 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use http::{
+    header::{IntoHeaderName, SET_COOKIE},
+    HeaderMap, HeaderName, HeaderValue, Request, Response,
+};
+use tower::{Layer, Service};
+
+/// Successful authentication of a subject, including the headers to be sent on the response like refreshed tokens or
+/// renewed cookies.
+#[derive(Debug, Clone)]
+pub struct Authenticated<S> {
+    /// The authenticated subject
+    pub subject: S,
+    /// The headers to include on the response
+    pub headers: HeaderMap,
+}
+
+impl<S> Authenticated<S> {
+    /// Creates a new authentication without response headers
+    pub fn new(subject: S) -> Self {
+        Self {
+            subject,
+            headers: HeaderMap::new(),
+        }
+    }
+
+    /// Appends a header to the response, invalid values are ignored
+    pub fn with_header(mut self, key: impl IntoHeaderName, value: impl TryInto<HeaderValue>) -> Self {
+        if let Ok(value) = value.try_into() {
+            self.headers.append(key, value);
+        }
+        self
+    }
+
+    /// Appends a `Set-Cookie` header to the response, invalid values are ignored
+    pub fn with_cookie(self, cookie: impl TryInto<HeaderValue>) -> Self {
+        self.with_header(SET_COOKIE, cookie)
+    }
+}
+
+/// Slot for the headers to include on the response, collected while authenticating the request.
+///
+/// It's added to the request extensions by [AuthResponseLayer] and filled by the [Auth](super::Auth) extractor.
+#[derive(Debug, Clone, Default)]
+pub struct AuthResponseHeaders(Arc<Mutex<HeaderMap>>);
+
+impl AuthResponseHeaders {
+    /// Appends the headers, skipping the ones already included with the same value
+    pub fn append(&self, headers: HeaderMap) {
+        let mut current = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        for (key, value) in iter_headers(headers) {
+            if !current.get_all(&key).iter().any(|v| *v == value) {
+                current.append(key, value);
+            }
+        }
+    }
+
+    /// Takes the collected headers, leaving the slot empty
+    pub fn take(&self) -> HeaderMap {
+        std::mem::take(&mut *self.0.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+/// Layer to apply the response headers returned by the authentication, like refreshed tokens or renewed cookies.
+///
+/// It must wrap every route where the [Auth](super::Auth) extractor is used, including the GraphQL HTTP handlers.
+/// Subscriptions over WebSocket can't include response headers, so they're dropped.
+///
+/// ``` rust ignore
+/// let router = Router::new()
+///     .route("/graphql", post(graphql_batch_handler::<Subject, (), Query, Mutation, Subscription>))
+///     .route("/api/me", get(me))
+///     .layer(AuthResponseLayer)
+///     .with_state(state);
+/// ```
+#[derive(Debug, Clone)]
+pub struct AuthResponseLayer;
+
+impl<S> Layer<S> for AuthResponseLayer {
+    type Service = AuthResponseService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AuthResponseService { inner }
+    }
+}
+
+/// Middleware to apply the authentication response headers.
+///
+/// See [AuthResponseLayer].
+#[derive(Debug, Clone)]
+pub struct AuthResponseService<S> {
+    inner: S,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for AuthResponseService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+    type Response = Response<ResBody>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        let slot = AuthResponseHeaders::default();
+        req.extensions_mut().insert(slot.clone());
+        let fut = self.inner.call(req);
+        Box::pin(async move {
+            let mut res = fut.await?;
+            let headers = res.headers_mut();
+            for (key, value) in iter_headers(slot.take()) {
+                headers.append(key, value);
+            }
+            Ok(res)
+        })
+    }
+}
+
+/// Iterates over every header value along with its name, as [HeaderMap] only yields the name on the first one
+fn iter_headers(headers: HeaderMap) -> impl Iterator<Item = (HeaderName, HeaderValue)> {
+    let mut last = None;
+    headers.into_iter().filter_map(move |(key, value)| {
+        if key.is_some() {
+            last = key;
+        }
+        last.clone().map(|key| (key, value))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, routing::get, Router};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::auth::{
+        test_support::{NoopAuthz, TestAuthn, TestState, TestSubject},
+        Auth,
+    };
+
+    #[tokio::test]
+    async fn test_auth_response_layer() {
+        let authn = TestAuthn::default().with_renewed_cookie("user=renewed; Max-Age=60");
+        let router = Router::new()
+            .route(
+                "/",
+                get(|Auth(subject): Auth<TestSubject>, Auth(_): Auth<TestSubject>| async move { subject.to_string() }),
+            )
+            .layer(AuthResponseLayer)
+            .with_state(TestState(authn, NoopAuthz));
+
+        let req = Request::get("/").header("x-user", "1").body(Body::empty()).unwrap();
+        let res = router.oneshot(req).await.unwrap();
+        let cookies = res.headers().get_all(SET_COOKIE).iter().collect::<Vec<_>>();
+        assert_eq!(cookies, ["user=renewed; Max-Age=60"]);
+    }
+}
@@ -0,0 +1,227 @@
+use std::{
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use axum::{
+    extract::FromRequestParts,
+    response::{IntoResponse, Response},
+};
+use http::{header::WWW_AUTHENTICATE, Request};
+use tower::{Layer, Service};
+
+use super::{Auth, AuthErrorCode, AuthState, SubjectScopes};
+use crate::error::{ApiError, Error, Result};
+
+/// Scopes required to perform an action, either all or any of them.
+///
+/// Granted scopes ending with `*` are wildcards, so `documents:*` grants `documents:read` and `documents:write` while
+/// `*` grants every scope.
+#[derive(Debug, Clone)]
+pub struct RequiredScopes {
+    scopes: Vec<&'static str>,
+    any: bool,
+}
+
+impl RequiredScopes {
+    /// Requires every one of the scopes
+    pub fn all(scopes: impl IntoIterator<Item = &'static str>) -> Self {
+        Self {
+            scopes: scopes.into_iter().collect(),
+            any: false,
+        }
+    }
+
+    /// Requires at least one of the scopes
+    pub fn any(scopes: impl IntoIterator<Item = &'static str>) -> Self {
+        Self {
+            scopes: scopes.into_iter().collect(),
+            any: true,
+        }
+    }
+
+    /// Checks whether the granted scopes satisfy the required ones
+    pub fn is_satisfied_by<'a>(&self, granted: impl IntoIterator<Item = &'a str>) -> bool {
+        let granted = granted.into_iter().collect::<Vec<_>>();
+        let is_granted = |required: &&str| granted.iter().any(|g| scope_matches(g, required));
+        if self.any {
+            self.scopes.is_empty() || self.scopes.iter().any(is_granted)
+        } else {
+            self.scopes.iter().all(is_granted)
+        }
+    }
+
+    /// Verifies that the subject has the required scopes, failing with
+    /// [AuthInsufficientScope](AuthErrorCode::AuthInsufficientScope) otherwise.
+    ///
+    /// The error doesn't include the `WWW-Authenticate` header, use [verify_api](Self::verify_api) when responding to
+    /// HTTP requests.
+    pub fn verify(&self, subject: &impl SubjectScopes) -> Result<()> {
+        if self.is_satisfied_by(subject.scopes()) {
+            Ok(())
+        } else {
+            Err(Error::new(AuthErrorCode::AuthInsufficientScope {
+                scope: self.scopes.join(" "),
+            })
+            .with_reason(format!("'{subject}' doesn't have the required scopes")))
+        }
+    }
+
+    /// Same as [verify](Self::verify), but failing with an [ApiError] including the `WWW-Authenticate` header
+    pub fn verify_api(&self, subject: &impl SubjectScopes) -> Result<(), Box<ApiError>> {
+        self.verify(subject)
+            .map_err(|err| Box::<ApiError>::from(err).with_header(WWW_AUTHENTICATE, self.www_authenticate()))
+    }
+
+    /// Builds the `WWW-Authenticate` header value for the insufficient scope error, as defined by
+    /// [RFC 6750](https://datatracker.ietf.org/doc/html/rfc6750#section-3)
+    pub fn www_authenticate(&self) -> String {
+        format!(r#"Bearer error="insufficient_scope", scope="{}""#, self.scopes.join(" "))
+    }
+}
+
+/// Checks whether the granted scope matches the required one, considering wildcards
+pub fn scope_matches(granted: &str, required: &str) -> bool {
+    match granted.strip_suffix('*') {
+        Some(prefix) => required.starts_with(prefix),
+        None => granted == required,
+    }
+}
+
+/// Layer to require the authenticated subject to have some scopes, before calling the inner service.
+///
+/// The subject is extracted with the [Auth] extractor and requests are rejected with an [ApiError], either
+/// `AUTH_MISSING` if the subject is not authenticated or `AUTH_INSUFFICIENT_SCOPE` including the `WWW-Authenticate`
+/// header if it doesn't have the required scopes.
+///
+/// ``` rust ignore
+/// let router = Router::new()
+///     .route("/documents", get(list_documents))
+///     .layer(RequireScopes::new(state.clone(), RequiredScopes::any(["documents:read", "documents:write"])))
+///     .with_state(state);
+/// ```
+pub struct RequireScopes<S, St> {
+    state: St,
+    scopes: RequiredScopes,
+    sub_type: PhantomData<fn() -> S>,
+}
+
+impl<S, St: Clone> Clone for RequireScopes<S, St> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+            scopes: self.scopes.clone(),
+            sub_type: PhantomData,
+        }
+    }
+}
+
+impl<S: SubjectScopes, St: AuthState<S> + Clone> RequireScopes<S, St> {
+    /// Creates a new layer requiring the given scopes
+    pub fn new(state: St, scopes: RequiredScopes) -> Self {
+        Self {
+            state,
+            scopes,
+            sub_type: PhantomData,
+        }
+    }
+}
+
+impl<S: SubjectScopes, St: AuthState<S> + Clone, I> Layer<I> for RequireScopes<S, St> {
+    type Service = RequireScopesService<S, St, I>;
+
+    fn layer(&self, inner: I) -> Self::Service {
+        RequireScopesService {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Middleware to verify the scopes of requests.
+///
+/// See [RequireScopes].
+pub struct RequireScopesService<S, St, I> {
+    inner: I,
+    layer: RequireScopes<S, St>,
+}
+
+impl<S, St: Clone, I: Clone> Clone for RequireScopesService<S, St, I> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+impl<S, St, I, B> Service<Request<B>> for RequireScopesService<S, St, I>
+where
+    S: SubjectScopes,
+    St: AuthState<S> + Clone,
+    I: Service<Request<B>, Response = Response> + Clone + Send + 'static,
+    I::Future: Send,
+    B: Send + 'static,
+{
+    type Error = I::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+    type Response = Response;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        // Take the service that was ready, leaving a clone in its place
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let layer = self.layer.clone();
+        Box::pin(async move {
+            let (mut parts, body) = req.into_parts();
+            let subject = match Auth::<S>::from_request_parts(&mut parts, &layer.state).await {
+                Ok(Auth(subject)) => subject,
+                Err(err) => return Ok(err.into_response()),
+            };
+            if let Err(err) = layer.scopes.verify_api(&subject) {
+                return Ok(err.into_response());
+            }
+            inner.call(Request::from_parts(parts, body)).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::test_support::TestSubject;
+
+    #[test]
+    fn test_required_scopes() {
+        let all = RequiredScopes::all(["documents:read", "users:read"]);
+        assert!(all.is_satisfied_by(["documents:read", "users:read", "other"]));
+        assert!(all.is_satisfied_by(["documents:*", "users:*"]));
+        assert!(all.is_satisfied_by(["*"]));
+        assert!(!all.is_satisfied_by(["documents:read"]));
+        assert!(!all.is_satisfied_by(["documents:write", "users:read"]));
+
+        let any = RequiredScopes::any(["documents:read", "documents:write"]);
+        assert!(any.is_satisfied_by(["documents:write"]));
+        assert!(any.is_satisfied_by(["documents:*"]));
+        assert!(!any.is_satisfied_by(["documents"]));
+        assert!(!any.is_satisfied_by(["users:*"]));
+
+        assert_eq!(
+            any.www_authenticate(),
+            r#"Bearer error="insufficient_scope", scope="documents:read documents:write""#
+        );
+
+        assert!(any.verify(&TestSubject::new("test").with_scopes(["documents:read"])).is_ok());
+        let err = any.verify(&TestSubject::new("test").with_scopes(["users:read"])).unwrap_err();
+        assert_eq!(err.info().code(), "AUTH_INSUFFICIENT_SCOPE");
+        let res = any.verify_api(&TestSubject::new("test").with_scopes(["users:read"])).unwrap_err().into_response();
+        assert_eq!(res.status(), http::StatusCode::FORBIDDEN);
+        assert!(res.headers()[WWW_AUTHENTICATE].to_str().unwrap().contains("insufficient_scope"));
+    }
+}
@@ -0,0 +1,737 @@
+//! [AuthenticationService] for server-side sessions.
+//!
+//! Clients receive an opaque random token on a cookie, while the [SessionStore] only keeps its SHA-256 hash as the
+//! session id. Sessions expire after being idle for some time or after an absolute lifetime, whichever comes first,
+//! and the idle expiration is renewed on every request. With a
+//! [sliding cookie](SessionAuthenticationService::with_sliding_cookie) the cookie is also re-issued when renewed.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    marker::PhantomData,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use auto_impl::auto_impl;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ring::{
+    digest,
+    rand::{SecureRandom, SystemRandom},
+};
+
+use super::{AuthCredentials, AuthErrorCode, Authenticated, AuthenticationService, Subject};
+use crate::error::{Error, Result};
+
+/// Stored server-side session
+#[derive(Debug, Clone)]
+pub struct Session {
+    /// Identifier of the session, the hash of the token sent to the client
+    pub id: String,
+    /// Identifier of the authenticated subject
+    pub subject: String,
+    /// Additional data of the session
+    pub data: serde_json::Value,
+    /// When the session was created
+    pub created_at: SystemTime,
+    /// When the session was last used
+    pub last_seen_at: SystemTime,
+    /// When the session expires, regardless of its usage
+    pub expires_at: SystemTime,
+}
+
+/// Storage of sessions
+#[auto_impl(Box, Arc)]
+#[trait_variant::make(Send)]
+pub trait SessionStore: Send + Sync + Sized + Clone + 'static {
+    /// Stores a new session
+    async fn insert(&self, session: Session) -> Result<()>;
+
+    /// Retrieves a session by its id
+    async fn find(&self, id: &str) -> Result<Option<Session>>;
+
+    /// Retrieves every session of the subject
+    async fn list(&self, subject: &str) -> Result<Vec<Session>>;
+
+    /// Updates the last time the session was used
+    async fn touch(&self, id: &str, last_seen_at: SystemTime) -> Result<()>;
+
+    /// Removes a session, returning whether it existed
+    async fn delete(&self, id: &str) -> Result<bool>;
+
+    /// Removes every session of the subject, returning how many were removed
+    async fn delete_by_subject(&self, subject: &str) -> Result<u64>;
+
+    /// Removes every session already expired or not used since the given time, returning how many were removed
+    async fn delete_expired(&self, idle_since: SystemTime) -> Result<u64>;
+}
+
+/// Expiration of the sessions that never expire (9999-12-31T23:59:59Z), so it can be stored by any [SessionStore]
+fn never_expires() -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(253_402_300_799)
+}
+
+/// Trait implemented by [Subject] types that can be built from an authenticated session
+pub trait FromSession: Subject {
+    /// Builds the subject from the session
+    fn from_session(session: &Session) -> Result<Self>;
+}
+
+/// `SameSite` attribute of the session cookie
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+impl fmt::Display for SameSite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// [AuthenticationService] validating server-side sessions, sent on the `session` cookie by default.
+///
+/// Unknown or expired sessions are rejected with [AuthErrorCode::AuthInvalidToken], so the
+/// [Auth](super::Auth) extractor removes the cookie.
+///
+/// ``` rust ignore
+/// let authn = SessionAuthenticationService::<MySubject, _>::new(PgSessionStore::new(pool))
+///     .with_idle_timeout(Duration::from_secs(30 * 60));
+///
+/// // On login
+/// let (token, session) = authn.create("user:1", serde_json::Value::Null).await?;
+/// let res = ([(SET_COOKIE, authn.cookie(&token, &session))], "Welcome");
+///
+/// // On logout
+/// authn.logout(&token).await?;
+/// let res = ([(SET_COOKIE, authn.removal_cookie())], "Bye");
+/// ```
+pub struct SessionAuthenticationService<S, St> {
+    store: St,
+    header_name: String,
+    cookie_name: String,
+    cookie_path: String,
+    cookie_secure: bool,
+    cookie_same_site: SameSite,
+    idle_timeout: Duration,
+    absolute_timeout: Duration,
+    renew_interval: Duration,
+    sliding_cookie: bool,
+    _subject: PhantomData<fn() -> S>,
+}
+
+impl<S, St: Clone> Clone for SessionAuthenticationService<S, St> {
+    fn clone(&self) -> Self {
+        Self {
+            store: self.store.clone(),
+            header_name: self.header_name.clone(),
+            cookie_name: self.cookie_name.clone(),
+            cookie_path: self.cookie_path.clone(),
+            cookie_secure: self.cookie_secure,
+            cookie_same_site: self.cookie_same_site,
+            idle_timeout: self.idle_timeout,
+            absolute_timeout: self.absolute_timeout,
+            renew_interval: self.renew_interval,
+            sliding_cookie: self.sliding_cookie,
+            _subject: PhantomData,
+        }
+    }
+}
+
+impl<S, St: SessionStore> SessionAuthenticationService<S, St> {
+    /// Creates a new service with the given store.
+    ///
+    /// By default, sessions expire after 30 minutes idle or 24 hours since created, and the token is read from the
+    /// `session` cookie or the `x-session-token` header.
+    pub fn new(store: St) -> Self {
+        Self {
+            store,
+            header_name: "x-session-token".into(),
+            cookie_name: "session".into(),
+            cookie_path: "/".into(),
+            cookie_secure: true,
+            cookie_same_site: SameSite::Lax,
+            idle_timeout: Duration::from_secs(30 * 60),
+            absolute_timeout: Duration::from_secs(24 * 60 * 60),
+            renew_interval: Duration::from_secs(60),
+            sliding_cookie: false,
+            _subject: PhantomData,
+        }
+    }
+
+    /// Modifies the header containing the session token
+    pub fn with_header_name(mut self, header_name: impl Into<String>) -> Self {
+        self.header_name = header_name.into();
+        self
+    }
+
+    /// Modifies the cookie containing the session token
+    pub fn with_cookie_name(mut self, cookie_name: impl Into<String>) -> Self {
+        self.cookie_name = cookie_name.into();
+        self
+    }
+
+    /// Modifies the `Path` of the session cookie, `/` by default
+    pub fn with_cookie_path(mut self, cookie_path: impl Into<String>) -> Self {
+        self.cookie_path = cookie_path.into();
+        self
+    }
+
+    /// Modifies whether the session cookie is `Secure`, enabled by default
+    pub fn with_secure_cookie(mut self, secure: bool) -> Self {
+        self.cookie_secure = secure;
+        self
+    }
+
+    /// Modifies the `SameSite` attribute of the session cookie, `Lax` by default
+    pub fn with_same_site(mut self, same_site: SameSite) -> Self {
+        self.cookie_same_site = same_site;
+        self
+    }
+
+    /// Modifies the time a session can be idle before expiring
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    /// Modifies the maximum lifetime of a session, regardless of its usage
+    pub fn with_absolute_timeout(mut self, absolute_timeout: Duration) -> Self {
+        self.absolute_timeout = absolute_timeout;
+        self
+    }
+
+    /// Modifies how often the idle expiration is renewed, one minute by default
+    pub fn with_renew_interval(mut self, renew_interval: Duration) -> Self {
+        self.renew_interval = renew_interval;
+        self
+    }
+
+    /// Enables the sliding cookie, which expires after the idle timeout and is re-issued whenever the session is
+    /// renewed.
+    ///
+    /// The renewed cookie is only sent if the [AuthResponseLayer](super::AuthResponseLayer) is applied.
+    pub fn with_sliding_cookie(mut self) -> Self {
+        self.sliding_cookie = true;
+        self
+    }
+
+    /// Retrieves the underlying store
+    pub fn store(&self) -> &St {
+        &self.store
+    }
+
+    /// Creates a new session for the subject, returning the token to be sent to the client along with the session
+    pub async fn create(&self, subject: impl Into<String>, data: serde_json::Value) -> Result<(String, Session)> {
+        let mut token = [0u8; 32];
+        SystemRandom::new()
+            .fill(&mut token)
+            .map_err(|_| Error::internal("Couldn't generate a random session token"))?;
+        let token = URL_SAFE_NO_PAD.encode(token);
+
+        let now = SystemTime::now();
+        let session = Session {
+            id: session_id(&token),
+            subject: subject.into(),
+            data,
+            created_at: now,
+            last_seen_at: now,
+            expires_at: now
+                .checked_add(self.absolute_timeout)
+                .map_or_else(never_expires, |expires_at| expires_at.min(never_expires())),
+        };
+        self.store.insert(session.clone()).await?;
+        Ok((token, session))
+    }
+
+    /// Builds the `Set-Cookie` header value to send the session token to the client
+    pub fn cookie(&self, token: &str, session: &Session) -> String {
+        let mut max_age = session.expires_at.duration_since(SystemTime::now()).unwrap_or_default();
+        if self.sliding_cookie {
+            max_age = max_age.min(self.idle_timeout);
+        }
+        self.build_cookie(token, max_age.as_secs())
+    }
+
+    /// Builds the `Set-Cookie` header value to remove the session cookie from the client
+    pub fn removal_cookie(&self) -> String {
+        self.build_cookie("", 0)
+    }
+
+    fn build_cookie(&self, value: &str, max_age: u64) -> String {
+        let mut cookie = format!(
+            "{}={value}; Path={}; Max-Age={max_age}; HttpOnly; SameSite={}",
+            self.cookie_name, self.cookie_path, self.cookie_same_site
+        );
+        if self.cookie_secure {
+            cookie.push_str("; Secure");
+        }
+        cookie
+    }
+
+    /// Removes the session of the given token
+    pub async fn logout(&self, token: &str) -> Result<()> {
+        self.store.delete(&session_id(token)).await?;
+        Ok(())
+    }
+
+    /// Removes a session by its id, returning whether it existed
+    pub async fn revoke(&self, id: &str) -> Result<bool> {
+        self.store.delete(id).await
+    }
+
+    /// Removes every session of the subject, returning how many were removed
+    pub async fn revoke_all(&self, subject: &str) -> Result<u64> {
+        self.store.delete_by_subject(subject).await
+    }
+
+    /// Retrieves the active sessions of the subject
+    pub async fn list(&self, subject: &str) -> Result<Vec<Session>> {
+        let now = SystemTime::now();
+        let mut sessions = self.store.list(subject).await?;
+        sessions.retain(|s| !self.is_expired(s, now));
+        Ok(sessions)
+    }
+
+    /// Removes every expired session from the store, returning how many were removed.
+    ///
+    /// Expired sessions are removed when used, but this method should be called periodically to remove the abandoned
+    /// ones.
+    pub async fn purge_expired(&self) -> Result<u64> {
+        // When the idle timeout is too large to be subtracted, no session has been idle for that long
+        let idle_since = SystemTime::now().checked_sub(self.idle_timeout).unwrap_or(UNIX_EPOCH);
+        self.store.delete_expired(idle_since).await
+    }
+
+    /// Checks whether the session is expired
+    fn is_expired(&self, session: &Session, now: SystemTime) -> bool {
+        session.expires_at <= now
+            || session
+                .last_seen_at
+                .checked_add(self.idle_timeout)
+                .is_some_and(|idle_expires_at| idle_expires_at <= now)
+    }
+
+    /// Validates the token, returning its session and whether it was renewed
+    async fn validate(&self, token: &str) -> Result<(Session, bool)> {
+        let id = session_id(token);
+        let Some(mut session) = self.store.find(&id).await? else {
+            return Err(Error::new(AuthErrorCode::AuthInvalidToken).with_reason("Unknown session"));
+        };
+
+        let now = SystemTime::now();
+        if self.is_expired(&session, now) {
+            self.store.delete(&id).await?;
+            return Err(Error::new(AuthErrorCode::AuthInvalidToken).with_reason("The session has expired"));
+        }
+
+        // Renew the idle expiration
+        let renewed = now.duration_since(session.last_seen_at).unwrap_or_default() >= self.renew_interval;
+        if renewed {
+            self.store.touch(&id, now).await?;
+            session.last_seen_at = now;
+        }
+
+        Ok((session, renewed))
+    }
+}
+
+impl<S: FromSession, St: SessionStore> AuthenticationService<S> for SessionAuthenticationService<S, St> {
+    fn header_name(&self) -> &str {
+        &self.header_name
+    }
+
+    fn cookie_name(&self) -> &str {
+        &self.cookie_name
+    }
+
+    fn removal_cookie(&self, _name: &str) -> String {
+        self.build_cookie("", 0)
+    }
+
+    async fn authenticate(&self, token: Option<&str>, cookie: Option<&str>) -> Result<S> {
+        let Some(token) = token.or(cookie) else {
+            return Err(Error::new(AuthErrorCode::AuthMissing).with_reason("Missing session"));
+        };
+        let (session, _) = self.validate(token).await?;
+        S::from_session(&session)
+    }
+
+    async fn authenticate_with_effects(&self, credentials: &AuthCredentials) -> Result<Authenticated<S>> {
+        let header = credentials.token(&self.header_name);
+        let cookie = credentials.cookie(&self.cookie_name);
+        let Some(token) = header.or(cookie) else {
+            return Err(Error::new(AuthErrorCode::AuthMissing).with_reason("Missing session"));
+        };
+        let (session, renewed) = self.validate(token).await?;
+        let authenticated = Authenticated::new(S::from_session(&session)?);
+        // Re-issue the cookie only when the session was sent on it
+        if self.sliding_cookie && renewed && header.is_none() {
+            Ok(authenticated.with_cookie(self.cookie(token, &session)))
+        } else {
+            Ok(authenticated)
+        }
+    }
+}
+
+/// Computes the session id from its token
+fn session_id(token: &str) -> String {
+    let hash = digest::digest(&digest::SHA256, token.as_bytes());
+    hash.as_ref().iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// [SessionStore] keeping the sessions in memory.
+///
+/// It can be cheaply cloned, sharing the same sessions.
+#[derive(Debug, Clone, Default)]
+pub struct InMemorySessionStore {
+    sessions: Arc<RwLock<HashMap<String, Session>>>,
+}
+
+impl InMemorySessionStore {
+    /// Creates a new empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SessionStore for InMemorySessionStore {
+    async fn insert(&self, session: Session) -> Result<()> {
+        self.sessions
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(session.id.clone(), session);
+        Ok(())
+    }
+
+    async fn find(&self, id: &str) -> Result<Option<Session>> {
+        Ok(self.sessions.read().unwrap_or_else(|e| e.into_inner()).get(id).cloned())
+    }
+
+    async fn list(&self, subject: &str) -> Result<Vec<Session>> {
+        let sessions = self.sessions.read().unwrap_or_else(|e| e.into_inner());
+        let mut ret = sessions
+            .values()
+            .filter(|s| s.subject == subject)
+            .cloned()
+            .collect::<Vec<_>>();
+        ret.sort_by_key(|s| s.created_at);
+        Ok(ret)
+    }
+
+    async fn touch(&self, id: &str, last_seen_at: SystemTime) -> Result<()> {
+        if let Some(session) = self.sessions.write().unwrap_or_else(|e| e.into_inner()).get_mut(id) {
+            session.last_seen_at = last_seen_at;
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, id: &str) -> Result<bool> {
+        Ok(self
+            .sessions
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(id)
+            .is_some())
+    }
+
+    async fn delete_by_subject(&self, subject: &str) -> Result<u64> {
+        let mut sessions = self.sessions.write().unwrap_or_else(|e| e.into_inner());
+        let len = sessions.len();
+        sessions.retain(|_, s| s.subject != subject);
+        Ok((len - sessions.len()) as u64)
+    }
+
+    async fn delete_expired(&self, idle_since: SystemTime) -> Result<u64> {
+        let now = SystemTime::now();
+        let mut sessions = self.sessions.write().unwrap_or_else(|e| e.into_inner());
+        let len = sessions.len();
+        sessions.retain(|_, s| s.expires_at > now && s.last_seen_at > idle_since);
+        Ok((len - sessions.len()) as u64)
+    }
+}
+
+#[cfg(feature = "postgres")]
+pub use self::postgres::PgSessionStore;
+
+#[cfg(feature = "postgres")]
+mod postgres {
+    use std::time::SystemTime;
+
+    use sqlx::{
+        types::chrono::{DateTime, Utc},
+        PgPool,
+    };
+
+    use super::{Session, SessionStore};
+    use crate::{
+        error::{MapToErr, Result},
+        sqlx::MapSqlxErr,
+    };
+
+    type SessionRow = (String, String, String, DateTime<Utc>, DateTime<Utc>, DateTime<Utc>);
+
+    /// [SessionStore] backed by a Postgres table, `sessions` by default.
+    ///
+    /// The table can be created with [create_table](PgSessionStore::create_table) or with a migration like:
+    ///
+    /// ``` sql
+    /// CREATE TABLE "sessions" (
+    ///     "id" CHAR(64) PRIMARY KEY,
+    ///     "subject" VARCHAR(255) NOT NULL,
+    ///     "data" JSONB NOT NULL,
+    ///     "created_at" TIMESTAMPTZ NOT NULL,
+    ///     "last_seen_at" TIMESTAMPTZ NOT NULL,
+    ///     "expires_at" TIMESTAMPTZ NOT NULL
+    /// );
+    /// CREATE INDEX "sessions_subject_idx" ON "sessions" ("subject");
+    /// ```
+    #[derive(Debug, Clone)]
+    pub struct PgSessionStore {
+        pool: PgPool,
+        table: String,
+    }
+
+    impl PgSessionStore {
+        /// Creates a new store on the `sessions` table
+        pub fn new(pool: PgPool) -> Self {
+            Self {
+                pool,
+                table: "sessions".into(),
+            }
+        }
+
+        /// Modifies the table name
+        pub fn with_table(mut self, table: impl Into<String>) -> Self {
+            self.table = table.into();
+            self
+        }
+
+        /// Creates the table, if it doesn't exist yet
+        pub async fn create_table(&self) -> Result<()> {
+            let table = &self.table;
+            sqlx::raw_sql(&format!(
+                r#"
+                CREATE TABLE IF NOT EXISTS "{table}" (
+                    "id" CHAR(64) PRIMARY KEY,
+                    "subject" VARCHAR(255) NOT NULL,
+                    "data" JSONB NOT NULL,
+                    "created_at" TIMESTAMPTZ NOT NULL,
+                    "last_seen_at" TIMESTAMPTZ NOT NULL,
+                    "expires_at" TIMESTAMPTZ NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS "{table}_subject_idx" ON "{table}" ("subject");
+                "#
+            ))
+            .execute(&self.pool)
+            .await
+            .map_sqlx_err("Couldn't create the sessions table")?;
+            Ok(())
+        }
+
+        fn select(&self, filter: &str) -> String {
+            format!(
+                r#"SELECT "id", "subject", "data"::TEXT, "created_at", "last_seen_at", "expires_at"
+                FROM "{}" WHERE {filter}"#,
+                self.table
+            )
+        }
+    }
+
+    impl SessionStore for PgSessionStore {
+        async fn insert(&self, session: Session) -> Result<()> {
+            sqlx::query(&format!(
+                r#"INSERT INTO "{}" ("id", "subject", "data", "created_at", "last_seen_at", "expires_at")
+                VALUES ($1, $2, $3::JSONB, $4, $5, $6)"#,
+                self.table
+            ))
+            .bind(session.id)
+            .bind(session.subject)
+            .bind(session.data.to_string())
+            .bind(DateTime::<Utc>::from(session.created_at))
+            .bind(DateTime::<Utc>::from(session.last_seen_at))
+            .bind(DateTime::<Utc>::from(session.expires_at))
+            .execute(&self.pool)
+            .await
+            .map_sqlx_err("Couldn't insert the session")?;
+            Ok(())
+        }
+
+        async fn find(&self, id: &str) -> Result<Option<Session>> {
+            let row = sqlx::query_as::<_, SessionRow>(&self.select(r#""id" = $1"#))
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await
+                .map_sqlx_err("Couldn't retrieve the session")?;
+            row.map(from_row).transpose()
+        }
+
+        async fn list(&self, subject: &str) -> Result<Vec<Session>> {
+            let rows = sqlx::query_as::<_, SessionRow>(&self.select(r#""subject" = $1 ORDER BY "created_at""#))
+                .bind(subject)
+                .fetch_all(&self.pool)
+                .await
+                .map_sqlx_err("Couldn't list the sessions")?;
+            rows.into_iter().map(from_row).collect()
+        }
+
+        async fn touch(&self, id: &str, last_seen_at: SystemTime) -> Result<()> {
+            sqlx::query(&format!(
+                r#"UPDATE "{}" SET "last_seen_at" = $2 WHERE "id" = $1"#,
+                self.table
+            ))
+            .bind(id)
+            .bind(DateTime::<Utc>::from(last_seen_at))
+            .execute(&self.pool)
+            .await
+            .map_sqlx_err("Couldn't update the session")?;
+            Ok(())
+        }
+
+        async fn delete(&self, id: &str) -> Result<bool> {
+            let res = sqlx::query(&format!(r#"DELETE FROM "{}" WHERE "id" = $1"#, self.table))
+                .bind(id)
+                .execute(&self.pool)
+                .await
+                .map_sqlx_err("Couldn't delete the session")?;
+            Ok(res.rows_affected() > 0)
+        }
+
+        async fn delete_by_subject(&self, subject: &str) -> Result<u64> {
+            let res = sqlx::query(&format!(r#"DELETE FROM "{}" WHERE "subject" = $1"#, self.table))
+                .bind(subject)
+                .execute(&self.pool)
+                .await
+                .map_sqlx_err("Couldn't delete the sessions")?;
+            Ok(res.rows_affected())
+        }
+
+        async fn delete_expired(&self, idle_since: SystemTime) -> Result<u64> {
+            let res = sqlx::query(&format!(
+                r#"DELETE FROM "{}" WHERE "expires_at" <= NOW() OR "last_seen_at" <= $1"#,
+                self.table
+            ))
+            .bind(DateTime::<Utc>::from(idle_since))
+            .execute(&self.pool)
+            .await
+            .map_sqlx_err("Couldn't delete the expired sessions")?;
+            Ok(res.rows_affected())
+        }
+    }
+
+    fn from_row((id, subject, data, created_at, last_seen_at, expires_at): SessionRow) -> Result<Session> {
+        Ok(Session {
+            id,
+            subject,
+            data: serde_json::from_str(&data).map_to_internal_err("Couldn't deserialize the session data")?,
+            created_at: created_at.into(),
+            last_seen_at: last_seen_at.into(),
+            expires_at: expires_at.into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::test_support::TestSubject;
+
+    #[tokio::test]
+    async fn test_session_authentication() {
+        let store = InMemorySessionStore::new();
+        let authn = SessionAuthenticationService::<TestSubject, _>::new(store.clone())
+            .with_renew_interval(Duration::ZERO)
+            .with_idle_timeout(Duration::from_secs(60));
+
+        let (token, session) = authn.create("user:1", serde_json::Value::Null).await.unwrap();
+        assert_ne!(token, session.id);
+        let cookie = authn.cookie(&token, &session);
+        assert!(cookie.starts_with(&format!("session={token}; Path=/; Max-Age=")));
+        assert!(cookie.ends_with("; HttpOnly; SameSite=Lax; Secure"));
+
+        // Authenticate with the cookie, renewing the session
+        let subject = authn.authenticate(None, Some(&token)).await.unwrap();
+        assert_eq!(subject.id, "user:1");
+        let renewed = store.find(&session.id).await.unwrap().unwrap();
+        assert!(renewed.last_seen_at > session.last_seen_at);
+
+        // Idle sessions expire
+        store
+            .touch(&session.id, SystemTime::now() - Duration::from_secs(120))
+            .await
+            .unwrap();
+        let err = authn.authenticate(None, Some(&token)).await.unwrap_err();
+        assert_eq!(err.info().code(), "AUTH_INVALID_TOKEN");
+        assert!(store.find(&session.id).await.unwrap().is_none());
+
+        // Logout
+        let (token, _) = authn.create("user:1", serde_json::Value::Null).await.unwrap();
+        authn.logout(&token).await.unwrap();
+        assert!(authn.authenticate(None, Some(&token)).await.is_err());
+
+        // Revoke all
+        let (first, _) = authn.create("user:1", serde_json::Value::Null).await.unwrap();
+        let (second, _) = authn.create("user:1", serde_json::Value::Null).await.unwrap();
+        let (other, _) = authn.create("user:2", serde_json::Value::Null).await.unwrap();
+        assert_eq!(authn.list("user:1").await.unwrap().len(), 2);
+        assert_eq!(authn.revoke_all("user:1").await.unwrap(), 2);
+        assert!(authn.authenticate(None, Some(&first)).await.is_err());
+        assert!(authn.authenticate(None, Some(&second)).await.is_err());
+        assert!(authn.authenticate(None, Some(&other)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_sliding_cookie() {
+        let authn = SessionAuthenticationService::<TestSubject, _>::new(InMemorySessionStore::new())
+            .with_renew_interval(Duration::ZERO)
+            .with_idle_timeout(Duration::from_secs(60))
+            .with_sliding_cookie();
+        let (token, session) = authn.create("user:1", serde_json::Value::Null).await.unwrap();
+        assert!(authn.cookie(&token, &session).contains("; Max-Age=60;"));
+
+        // The cookie is re-issued when the session is renewed from it
+        let credentials = AuthCredentials::new().with_cookie("session", &token);
+        let authenticated = authn.authenticate_with_effects(&credentials).await.unwrap();
+        assert_eq!(authenticated.subject.id, "user:1");
+        let cookie = authenticated.headers[http::header::SET_COOKIE].to_str().unwrap();
+        assert!(cookie.starts_with(&format!("session={token}; Path=/; Max-Age=60;")));
+
+        // But not when sent on the header
+        let credentials = AuthCredentials::new().with_token("x-session-token", &token);
+        let authenticated = authn.authenticate_with_effects(&credentials).await.unwrap();
+        assert!(authenticated.headers.is_empty());
+    }
+
+    #[test]
+    fn test_removal_cookie() {
+        let authn = SessionAuthenticationService::<TestSubject, _>::new(InMemorySessionStore::new())
+            .with_cookie_path("/app");
+        let cookie = AuthenticationService::removal_cookie(&authn, "session");
+        assert!(cookie.starts_with("session=; Path=/app; Max-Age=0;"));
+        assert_eq!(cookie, authn.removal_cookie());
+    }
+
+    #[tokio::test]
+    async fn test_absolute_expiration() {
+        let store = InMemorySessionStore::new();
+        let authn =
+            SessionAuthenticationService::<TestSubject, _>::new(store.clone()).with_absolute_timeout(Duration::ZERO);
+        let (token, _) = authn.create("user:1", serde_json::Value::Null).await.unwrap();
+        assert!(authn.authenticate(None, Some(&token)).await.is_err());
+
+        let (_, session) = authn.create("user:1", serde_json::Value::Null).await.unwrap();
+        assert_eq!(authn.purge_expired().await.unwrap(), 1);
+        assert!(store.find(&session.id).await.unwrap().is_none());
+
+        // Timeouts too large to be represented never expire
+        let authn = SessionAuthenticationService::<TestSubject, _>::new(store.clone())
+            .with_idle_timeout(Duration::MAX)
+            .with_absolute_timeout(Duration::MAX);
+        let (token, session) = authn.create("user:1", serde_json::Value::Null).await.unwrap();
+        assert_eq!(session.expires_at, never_expires());
+        assert!(authn.authenticate(None, Some(&token)).await.is_ok());
+        assert_eq!(authn.purge_expired().await.unwrap(), 0);
+    }
+}
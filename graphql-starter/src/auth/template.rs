@@ -0,0 +1,54 @@
+use crate::error::{Error, GenericErrorCode, Result};
+
+/// Characters separating the type, id, relation and subject of the objects
+const OBJECT_SEPARATORS: [char; 3] = [':', '#', '@'];
+
+/// Fills the `{name}` placeholders of the template with the given values
+pub(crate) fn fill_template(template: &str, value: impl Fn(&str) -> Result<Option<String>>) -> Result<String> {
+    let mut ret = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 1..start + len];
+        let value = value(name)?
+            .ok_or_else(|| Error::internal(format!("Missing value for '{name}' on template '{template}'")))?;
+        ret.push_str(&rest[..start]);
+        ret.push_str(&value);
+        rest = &rest[start + len + 1..];
+    }
+    ret.push_str(rest);
+    Ok(ret)
+}
+
+/// Validates a value sent by the client to fill a template, which can't include any separator to prevent it from
+/// changing the object being authorized
+pub(crate) fn client_value(name: &str, value: impl Into<String>) -> Result<String> {
+    let value = value.into();
+    if value.contains(OBJECT_SEPARATORS) {
+        return Err(Error::new(GenericErrorCode::BadRequest).with_reason(format!("Invalid '{name}' value: {value}")));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_template() {
+        let value = |name: &str| match name {
+            "id" => client_value(name, "1").map(Some),
+            "parent.owner" => Ok(Some("2".to_owned())),
+            "other" => client_value(name, "doc:2#owner").map(Some),
+            _ => Ok(None),
+        };
+        assert_eq!(fill_template("document:{id}", value).unwrap(), "document:1");
+        assert_eq!(fill_template("user:{parent.owner}#{id}", value).unwrap(), "user:2#1");
+        assert_eq!(fill_template("documents", value).unwrap(), "documents");
+        assert!(fill_template("document:{missing}", value).is_err());
+        let err = fill_template("document:{other}", value).unwrap_err();
+        assert_eq!(err.info().status(), http::StatusCode::BAD_REQUEST);
+    }
+}
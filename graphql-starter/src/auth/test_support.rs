@@ -0,0 +1,157 @@
+//! Auth types shared by the tests
+
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use super::{
+    AuthCredentials, AuthErrorCode, AuthState, Authenticated, AuthenticationService, AuthorizationService, Subject,
+    SubjectScopes, WithAuthScheme,
+};
+use crate::error::{Error, Result};
+
+/// Subject identified by its id, with the granted scopes and the scheme that authenticated it
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TestSubject {
+    pub(crate) id: String,
+    pub(crate) scopes: Vec<String>,
+    pub(crate) scheme: &'static str,
+}
+
+impl TestSubject {
+    /// Creates a new subject without scopes
+    pub(crate) fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Grants the given scopes to the subject
+    pub(crate) fn with_scopes(mut self, scopes: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.scopes = scopes.into_iter().map(Into::into).collect();
+        self
+    }
+}
+
+impl fmt::Display for TestSubject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.id)
+    }
+}
+
+impl Subject for TestSubject {}
+
+impl SubjectScopes for TestSubject {
+    fn scopes(&self) -> Vec<&str> {
+        self.scopes.iter().map(String::as_str).collect()
+    }
+}
+
+impl WithAuthScheme for TestSubject {
+    fn with_auth_scheme(mut self, scheme: &'static str) -> Self {
+        self.scheme = scheme;
+        self
+    }
+}
+
+#[cfg(feature = "api-key")]
+impl super::FromApiKey for TestSubject {
+    fn from_api_key(record: &super::ApiKeyRecord) -> Result<Self> {
+        Ok(Self {
+            scopes: record.scopes.clone(),
+            ..Self::new(&record.subject)
+        })
+    }
+}
+
+#[cfg(feature = "jwt")]
+impl super::FromJwtClaims for TestSubject {
+    fn from_claims(claims: super::JwtClaims) -> Result<Self> {
+        Ok(Self::new(claims.sub.unwrap_or_default()))
+    }
+}
+
+#[cfg(feature = "session")]
+impl super::FromSession for TestSubject {
+    fn from_session(session: &super::Session) -> Result<Self> {
+        Ok(Self::new(&session.subject))
+    }
+}
+
+/// Service authenticating the `x-user` header or `user` cookie as the user with the same id, counting the calls
+#[derive(Clone, Default)]
+pub(crate) struct TestAuthn {
+    calls: Arc<AtomicUsize>,
+    renewed_cookie: Option<&'static str>,
+}
+
+impl TestAuthn {
+    /// Sets the cookie to be returned on every successful authentication
+    pub(crate) fn with_renewed_cookie(mut self, cookie: &'static str) -> Self {
+        self.renewed_cookie = Some(cookie);
+        self
+    }
+
+    /// Retrieves the number of authentications performed
+    pub(crate) fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+impl AuthenticationService<TestSubject> for TestAuthn {
+    fn header_name(&self) -> &str {
+        "x-user"
+    }
+
+    fn cookie_name(&self) -> &str {
+        "user"
+    }
+
+    async fn authenticate(&self, token: Option<&str>, cookie: Option<&str>) -> Result<TestSubject> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        match token.or(cookie) {
+            Some(id) => Ok(TestSubject::new(format!("user:{id}"))),
+            None => Err(Error::new(AuthErrorCode::AuthMissing)),
+        }
+    }
+
+    async fn authenticate_with_effects(&self, credentials: &AuthCredentials) -> Result<Authenticated<TestSubject>> {
+        let subject = self.authenticate_credentials(credentials).await?;
+        Ok(match self.renewed_cookie {
+            Some(cookie) => Authenticated::new(subject).with_cookie(cookie),
+            None => Authenticated::new(subject),
+        })
+    }
+}
+
+/// Authorization service allowing everything
+#[derive(Clone, Default)]
+pub(crate) struct NoopAuthz;
+
+impl AuthorizationService<TestSubject> for NoopAuthz {
+    async fn authorize(&self, _subject: &TestSubject, _relation: &str, _object: &str) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// State with the [TestAuthn] and the given authorization service
+#[derive(Clone)]
+pub(crate) struct TestState<Z = NoopAuthz>(pub(crate) TestAuthn, pub(crate) Z);
+
+impl<Z: AuthorizationService<TestSubject>> AuthState<TestSubject> for TestState<Z> {
+    type Authn = TestAuthn;
+    type Authz = Z;
+
+    fn authn(&self) -> &Self::Authn {
+        &self.0
+    }
+
+    fn authz(&self) -> &Self::Authz {
+        &self.1
+    }
+}
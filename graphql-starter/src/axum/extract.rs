@@ -11,9 +11,11 @@ use axum::{
 };
 use bytes::{BufMut, BytesMut};
 use error_info::ErrorInfo;
-use http::{header, request::Parts, HeaderValue, StatusCode};
+use http::{header, request::Parts, HeaderMap, HeaderValue, StatusCode};
 use serde::{de::DeserializeOwned, Serialize};
 
+#[cfg(feature = "garde")]
+use crate::error::Error;
 use crate::error::{ApiError, GenericErrorCode, MapToErr};
 
 /// Wrapper over [axum::Json] to customize error responses
@@ -194,6 +196,52 @@ where
     }
 }
 
+/// Wrapper over [Json] and [Query] extractors (or GraphQL inputs) that validates the extracted value with [garde].
+///
+/// Validation failures are responded as `422 Unprocessable Entity` errors, including the messages of every invalid
+/// field (see [ValidationErrorCode](crate::error::ValidationErrorCode)).
+///
+/// ``` rust ignore
+/// async fn handler(Validated(Json(body)): Validated<Json<CreateUser>>) -> ApiResult<Json<User>> {
+///     ...
+/// }
+/// ```
+#[cfg(feature = "garde")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Validated<T>(pub T);
+
+#[cfg(feature = "garde")]
+impl<S, T> FromRequest<S> for Validated<Json<T>>
+where
+    T: DeserializeOwned + garde::Validate,
+    T::Context: Default,
+    S: Send + Sync,
+{
+    type Rejection = Box<ApiError>;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = <Json<T> as FromRequest<S>>::from_request(req, state).await?;
+        value.validate().map_err(Box::<Error>::from)?;
+        Ok(Validated(Json(value)))
+    }
+}
+
+#[cfg(feature = "garde")]
+impl<S, T> FromRequestParts<S> for Validated<Query<T>>
+where
+    T: DeserializeOwned + garde::Validate,
+    T::Context: Default,
+    S: Send + Sync,
+{
+    type Rejection = Box<ApiError>;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Query(value) = <Query<T> as FromRequestParts<S>>::from_request_parts(parts, state).await?;
+        value.validate().map_err(Box::<Error>::from)?;
+        Ok(Validated(Query(value)))
+    }
+}
+
 /// Extractor for an optional `Accept-Languages` header
 #[derive(Debug, Clone, Default)]
 pub struct AcceptLanguage(pub Option<Arc<Vec<String>>>);
@@ -207,6 +255,16 @@ impl AcceptLanguage {
     pub fn preferred_language(&self) -> Option<&str> {
         self.accepted_languages().and_then(|l| l.first().map(|s| s.as_str()))
     }
+
+    /// Parses the `Accept-Language` header from the given headers (if any)
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let accept_language = headers
+            .get(http::header::ACCEPT_LANGUAGE)
+            .and_then(|v| v.to_str().ok().map(accept_language::parse).map(Arc::new))
+            .filter(|v| !v.is_empty());
+
+        AcceptLanguage(accept_language)
+    }
 }
 
 impl<S> FromRequestParts<S> for AcceptLanguage
@@ -216,13 +274,6 @@ where
     type Rejection = Box<ApiError>;
 
     async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
-        // Extract the header and parse it (if any)
-        let accept_language = parts
-            .headers
-            .get(http::header::ACCEPT_LANGUAGE)
-            .and_then(|v| v.to_str().ok().map(accept_language::parse).map(Arc::new))
-            .filter(|v| !v.is_empty());
-
-        Ok(AcceptLanguage(accept_language))
+        Ok(AcceptLanguage::from_headers(&parts.headers))
     }
 }
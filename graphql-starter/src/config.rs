@@ -75,6 +75,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::result_large_err)]
     fn test_config() {
         figment::Jail::expect_with(|jail| {
             let tmp_dir = jail.directory();
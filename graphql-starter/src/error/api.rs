@@ -1,59 +1,89 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
 use axum::{
     http::StatusCode,
     response::{IntoResponse, Response},
+    Extension,
 };
 use error_info::ErrorInfo;
-use http::{header::IntoHeaderName, HeaderMap, HeaderValue};
-use serde::Serialize;
+use http::{
+    header::{self, IntoHeaderName},
+    HeaderMap, HeaderValue,
+};
+use serde::{Deserialize, Serialize};
 
 use super::{Error, GenericErrorCode};
 use crate::axum::extract::Json;
 
 pub type ApiResult<T, E = Box<ApiError>> = std::result::Result<T, E>;
 
-/// An RFC-7807 compatible error implementing axum's [IntoResponse]
-#[derive(Debug, Serialize)]
+/// Media type of the [ApiError] responses
+pub const APPLICATION_PROBLEM_JSON: &str = "application/problem+json";
+
+/// Members defined by the RFC, that can't be used as extension members
+const RESERVED_MEMBERS: [&str; 7] = ["type", "title", "status", "detail", "instance", "info", "errors"];
+
+/// An [RFC-9457](https://www.rfc-editor.org/rfc/rfc9457) _Problem Details_ error implementing axum's [IntoResponse]
+///
+/// The error is also included on the response extensions, so it can be post-processed by
+/// [ApiErrorLayer](super::ApiErrorLayer), which can also populate the `type` and `instance` members.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApiError {
+    /// A URI reference that identifies the problem type, `about:blank` when not present
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    r#type: Option<String>,
     /// A short, human-readable title for the general error type
+    #[serde(default)]
     title: String,
     /// Conveying the HTTP status code
-    #[serde(serialize_with = "serialize_status_u16")]
+    #[serde(serialize_with = "serialize_status_u16", deserialize_with = "deserialize_status_u16")]
     status: StatusCode,
     /// A human-readable description of the specific error
+    #[serde(default)]
     detail: String,
+    /// A URI reference that identifies the specific occurrence of the problem
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instance: Option<String>,
     /// Additional information about the error
-    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     info: HashMap<String, String>,
     /// Additional details for each one of the errors encountered
-    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     errors: HashMap<String, serde_json::Value>,
+    /// Additional members of the problem
+    #[serde(flatten)]
+    extensions: HashMap<String, serde_json::Value>,
     /// Additional headers to be sent with the response
     #[serde(skip)]
     headers: Option<HeaderMap>,
+    /// The core error this one was built from, kept for debugging purposes
+    #[serde(skip)]
+    source: Option<Arc<Error>>,
 }
 
 impl ApiError {
     /// Builds a new error from the detail message
     pub fn new(status: StatusCode, detail: impl Into<String>) -> Box<Self> {
         Box::new(ApiError {
+            r#type: None,
             title: status
                 .canonical_reason()
                 .unwrap_or(GenericErrorCode::InternalServerError.raw_message())
                 .to_owned(),
             status,
             detail: detail.into(),
+            instance: None,
             info: Default::default(),
             errors: Default::default(),
+            extensions: Default::default(),
             headers: None,
+            source: None,
         })
     }
 
     /// Builds a new [ApiError] from the core [Error]
-    #[allow(clippy::boxed_local)]
     pub fn from_err(err: Box<Error>) -> Box<Self> {
-        let err = *err;
+        let err: Arc<Error> = Arc::from(err);
 
         // Trace error before losing context information, this should usually happen just before returning to clients
         if err.unexpected {
@@ -79,21 +109,58 @@ impl ApiError {
         }
 
         // Extend with the error properties
-        if let Some(properties) = err.properties {
+        if let Some(properties) = &err.properties {
             for (key, value) in properties {
-                ret = ret.with_error_info(key, value);
+                // Validation errors are included for each field
+                #[cfg(feature = "garde")]
+                if key == super::VALIDATION_ERRORS
+                    && let serde_json::Value::Object(fields) = value
+                {
+                    for (field, errors) in fields {
+                        ret = ret.with_error_info(field, errors.clone());
+                    }
+                    continue;
+                }
+                ret = ret.with_error_info(key, value.clone());
+            }
+        }
+
+        // Include the retry hint
+        if let Some(retry) = err.retry {
+            ret = ret.with_extension("retryable", true.into());
+            if let Some(after) = retry.after_secs() {
+                ret = ret.with_header(header::RETRY_AFTER, after.to_string());
             }
         }
 
+        ret.source = Some(err);
         ret
     }
 
+    /// Modify the problem type URI
+    pub fn with_type(mut self: Box<Self>, r#type: impl Into<String>) -> Box<Self> {
+        self.r#type = Some(r#type.into());
+        self
+    }
+
     /// Modify the title
     pub fn with_title(mut self: Box<Self>, title: impl Into<String>) -> Box<Self> {
         self.title = title.into();
         self
     }
 
+    /// Modify the detail
+    pub fn with_detail(mut self: Box<Self>, detail: impl Into<String>) -> Box<Self> {
+        self.detail = detail.into();
+        self
+    }
+
+    /// Modify the URI of the problem occurrence
+    pub fn with_instance(mut self: Box<Self>, instance: impl Into<String>) -> Box<Self> {
+        self.instance = Some(instance.into());
+        self
+    }
+
     /// Extend the error with additional information
     pub fn with_info(mut self: Box<Self>, key: impl Into<String>, value: impl Into<String>) -> Box<Self> {
         self.info.insert(key.into(), value.into());
@@ -106,6 +173,17 @@ impl ApiError {
         self
     }
 
+    /// Extend the error with an additional member, it will be ignored if the name is reserved
+    pub fn with_extension(mut self: Box<Self>, name: impl Into<String>, value: serde_json::Value) -> Box<Self> {
+        let name = name.into();
+        if RESERVED_MEMBERS.contains(&name.as_str()) {
+            tracing::error!("Problem details can't contain a reserved member as an extension: {name}");
+        } else {
+            self.extensions.insert(name, value);
+        }
+        self
+    }
+
     /// Extend the error with an additional header
     pub fn with_header(mut self: Box<Self>, key: impl IntoHeaderName, value: impl TryInto<HeaderValue>) -> Box<Self> {
         if let Ok(value) = value.try_into() {
@@ -115,6 +193,11 @@ impl ApiError {
         self
     }
 
+    /// Retrieves the problem type URI
+    pub fn r#type(&self) -> Option<&str> {
+        self.r#type.as_deref()
+    }
+
     /// Retrieves the error title
     pub fn title(&self) -> &str {
         &self.title
@@ -130,20 +213,49 @@ impl ApiError {
         &self.detail
     }
 
+    /// Retrieves the URI of the problem occurrence
+    pub fn instance(&self) -> Option<&str> {
+        self.instance.as_deref()
+    }
+
     /// Retrieves the error info
     pub fn info(&self) -> &HashMap<String, String> {
         &self.info
     }
 
+    /// Retrieves the error code, if the error was built from an [ErrorInfo]
+    pub fn error_code(&self) -> Option<&str> {
+        self.info.get("errorCode").map(String::as_str)
+    }
+
+    /// Retrieves the fields used on the error message, if the error was built from an [ErrorInfo]
+    pub fn message_fields(&self) -> HashMap<String, String> {
+        self.info
+            .iter()
+            .filter(|(k, _)| *k != "errorCode" && *k != "rawMessage")
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+
     /// Retrieves the internal errors
     pub fn errors(&self) -> &HashMap<String, serde_json::Value> {
         &self.errors
     }
 
+    /// Retrieves the extension members
+    pub fn extensions(&self) -> &HashMap<String, serde_json::Value> {
+        &self.extensions
+    }
+
     /// Retrieves the additional headers
     pub fn headers(&self) -> &Option<HeaderMap> {
         &self.headers
     }
+
+    /// Retrieves the core [Error] this one was built from (if any)
+    pub fn source(&self) -> Option<&Error> {
+        self.source.as_deref()
+    }
 }
 
 impl From<Box<Error>> for Box<ApiError> {
@@ -153,12 +265,18 @@ impl From<Box<Error>> for Box<ApiError> {
 }
 
 impl IntoResponse for Box<ApiError> {
-    fn into_response(mut self) -> Response {
-        if let Some(headers) = self.headers.take() {
-            (self.status, headers, Json(self)).into_response()
+    fn into_response(self) -> Response {
+        let extension = Extension(self.clone());
+        let mut res = if let Some(headers) = &self.headers {
+            (self.status, headers.clone(), extension, Json(self)).into_response()
         } else {
-            (self.status, Json(self)).into_response()
-        }
+            (self.status, extension, Json(self)).into_response()
+        };
+        res.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(APPLICATION_PROBLEM_JSON),
+        );
+        res
     }
 }
 
@@ -168,3 +286,11 @@ where
 {
     serializer.serialize_u16(status.as_u16())
 }
+
+fn deserialize_status_u16<'de, D>(deserializer: D) -> Result<StatusCode, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let status = u16::deserialize(deserializer)?;
+    StatusCode::from_u16(status).map_err(serde::de::Error::custom)
+}
@@ -0,0 +1,152 @@
+use std::{fs, io, path::Path, sync::LazyLock};
+
+use http::StatusCode;
+use serde::Serialize;
+
+use crate::axum::extract::Json;
+
+static ERROR_CATALOG: LazyLock<ErrorCatalog> = LazyLock::new(ErrorCatalog::collect);
+
+/// Entry of the [ErrorCatalog], describing one of the declared [ErrorInfo](error_info::ErrorInfo) variants.
+///
+/// Its properties are named after the extensions included on GraphQL errors and the info of [ApiError](super::ApiError)
+/// responses, so clients can easily match them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorCatalogEntry {
+    /// The HTTP status code
+    pub status_code: u16,
+    /// The canonical reason of the status code
+    pub status_kind: Option<&'static str>,
+    /// The unique error code
+    pub error_code: &'static str,
+    /// The message template, without variable replacements
+    pub raw_message: &'static str,
+    /// The fields to be replaced on the message template
+    pub message_fields: Vec<&'static str>,
+}
+
+impl ErrorCatalogEntry {
+    fn new(status: StatusCode, code: &'static str, raw_message: &'static str) -> Self {
+        Self {
+            status_code: status.as_u16(),
+            status_kind: status.canonical_reason(),
+            error_code: code,
+            raw_message,
+            message_fields: message_fields(raw_message),
+        }
+    }
+}
+
+/// Catalog of every [ErrorInfo](error_info::ErrorInfo) declared on the binary, sorted by status and code.
+///
+/// It's built from the `error-info-summary` feature, so every error enum deriving `ErrorInfo` is included,
+/// regardless of the crate where it's declared.
+#[derive(Debug, Clone, Serialize)]
+#[serde(transparent)]
+pub struct ErrorCatalog {
+    entries: Vec<ErrorCatalogEntry>,
+}
+
+impl ErrorCatalog {
+    /// Collects a new [ErrorCatalog] from every declared error
+    pub fn collect() -> Self {
+        let mut entries: Vec<ErrorCatalogEntry> = Vec::new();
+        for summary in error_info::summary() {
+            if entries.iter().any(|e| e.error_code == summary.code) {
+                tracing::warn!("Error code '{}' is declared more than once", summary.code);
+                continue;
+            }
+            entries.push(ErrorCatalogEntry::new(summary.status, summary.code, summary.raw_message));
+        }
+        Self { entries }
+    }
+
+    /// Retrieves the global [ErrorCatalog], collected only once
+    pub fn global() -> &'static Self {
+        &ERROR_CATALOG
+    }
+
+    /// Retrieves the catalog entries
+    pub fn entries(&self) -> &[ErrorCatalogEntry] {
+        &self.entries
+    }
+
+    /// Retrieves the entry for the given error code (if any)
+    pub fn get(&self, error_code: &str) -> Option<&ErrorCatalogEntry> {
+        self.entries.iter().find(|e| e.error_code == error_code)
+    }
+
+    /// Serializes the catalog as a pretty-printed JSON array
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("the catalog is always serializable")
+    }
+}
+
+/// Exports the global [ErrorCatalog] as JSON to the provided path.
+///
+/// It can be used alongside `graphql::export_graphql_sdl` (with the `graphql` feature) to generate the base file for
+/// i18n or client error handling when building the API.
+pub fn export_error_catalog(path: impl AsRef<Path>) -> io::Result<()> {
+    fs::write(path, ErrorCatalog::global().to_json())
+}
+
+/// Handler that returns the global [ErrorCatalog] as JSON.
+///
+/// ``` rust ignore
+/// let router = Router::new().route("/errors", get(error_catalog_handler));
+/// ```
+pub async fn error_catalog_handler() -> Json<&'static ErrorCatalog> {
+    Json(ErrorCatalog::global())
+}
+
+/// Extracts the `{field}` variables from a message template, in order of appearance
+fn message_fields(raw_message: &'static str) -> Vec<&'static str> {
+    let mut fields = Vec::new();
+    let mut rest = raw_message;
+    while let Some(start) = rest.find('{') {
+        rest = &rest[start + 1..];
+        let Some(end) = rest.find('}') else {
+            break;
+        };
+        let field = &rest[..end];
+        if !field.is_empty() && !fields.contains(&field) {
+            fields.push(field);
+        }
+        rest = &rest[end + 1..];
+    }
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalog() {
+        let catalog = ErrorCatalog::global();
+
+        let not_found = catalog.get("NOT_FOUND").expect("generic errors are included");
+        assert_eq!(not_found.status_code, 404);
+        assert_eq!(not_found.status_kind, Some("Not Found"));
+        assert_eq!(not_found.raw_message, "The resource could not be found");
+        assert!(not_found.message_fields.is_empty());
+
+        let exceeds_limit = catalog
+            .get("PAGE_EXCEEDS_LIMIT")
+            .expect("pagination errors are included");
+        assert_eq!(exceeds_limit.message_fields, vec!["field", "max"]);
+
+        let mut sorted = catalog.entries().to_vec();
+        sorted.sort_by_key(|e| (e.status_code, e.error_code));
+        assert_eq!(sorted, catalog.entries());
+    }
+
+    #[test]
+    fn test_message_fields() {
+        assert_eq!(message_fields("No fields"), Vec::<&str>::new());
+        assert_eq!(message_fields("Missing \"{a}\" and {b} or {a}"), vec!["a", "b"]);
+        assert_eq!(message_fields("Unclosed {field"), Vec::<&str>::new());
+    }
+}
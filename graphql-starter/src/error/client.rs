@@ -0,0 +1,287 @@
+//! Utilities to call other services built on this crate, rebuilding the [Error] from their [ApiError] responses.
+
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    pin::Pin,
+    sync::{LazyLock, Mutex},
+    task::{Context, Poll},
+};
+
+use axum::body::{Body, HttpBody};
+use bytes::Bytes;
+use error_info::ErrorInfo;
+use http::{header, HeaderValue, Request, Response, StatusCode};
+use tower::{BoxError, Layer, Service};
+
+use super::{interpolate, ApiError, Error, GenericErrorCode};
+use crate::request_id::{RequestId, REQUEST_ID_HEADER};
+
+/// Maximum number of distinct error codes and messages interned for [DynamicErrorInfo]
+const MAX_INTERNED: usize = 4096;
+
+/// Maximum size of the error responses to be parsed
+const MAX_ERROR_BODY_SIZE: usize = 1024 * 1024;
+
+static INTERNED: LazyLock<Mutex<HashSet<&'static str>>> = LazyLock::new(Default::default);
+
+/// Interns the given string, so it can be used where a `&'static str` is required.
+///
+/// Error codes and raw messages are a bounded set, but to prevent misbehaving services from exhausting the memory, no
+/// more than [MAX_INTERNED] strings are kept.
+fn intern(value: &str) -> Option<&'static str> {
+    let mut interned = INTERNED.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(value) = interned.get(value) {
+        Some(*value)
+    } else if interned.len() < MAX_INTERNED {
+        let value: &'static str = Box::leak(value.to_owned().into_boxed_str());
+        interned.insert(value);
+        Some(value)
+    } else {
+        tracing::warn!("Too many distinct error codes and messages, '{value}' couldn't be interned");
+        None
+    }
+}
+
+/// [ErrorInfo] built at runtime, for errors returned by other services
+#[derive(Debug, Clone)]
+pub struct DynamicErrorInfo {
+    status: StatusCode,
+    code: &'static str,
+    raw_message: &'static str,
+    fields: HashMap<String, String>,
+}
+
+impl DynamicErrorInfo {
+    /// Creates a new [DynamicErrorInfo]
+    pub fn new(status: StatusCode, code: &str, raw_message: &str, fields: HashMap<String, String>) -> Self {
+        let fallback = GenericErrorCode::InternalServerError;
+        Self {
+            status,
+            code: intern(code).unwrap_or(fallback.code()),
+            raw_message: intern(raw_message).unwrap_or(fallback.raw_message()),
+            fields,
+        }
+    }
+}
+
+impl ErrorInfo for DynamicErrorInfo {
+    fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    fn code(&self) -> &'static str {
+        self.code
+    }
+
+    fn raw_message(&self) -> &'static str {
+        self.raw_message
+    }
+
+    fn fields(&self) -> HashMap<String, String> {
+        self.fields.clone()
+    }
+
+    fn message(&self) -> String {
+        interpolate(self.raw_message, |name| self.fields.get(name))
+    }
+}
+
+/// Rebuilds the [Error] from an [ApiError] returned by another service, keeping its status, code and message
+/// fields.
+impl From<Box<ApiError>> for Box<Error> {
+    #[track_caller]
+    fn from(err: Box<ApiError>) -> Self {
+        let status = err.status();
+        let code = err
+            .error_code()
+            .map(ToOwned::to_owned)
+            .unwrap_or_else(|| status_code(status));
+        let raw_message = err.info().get("rawMessage").map(String::as_str).unwrap_or(err.detail());
+        let info = DynamicErrorInfo::new(status, &code, raw_message, err.message_fields());
+
+        let mut ret = Error::new(info).with_reason(format!("Upstream error: {}", err.detail()));
+        for (key, value) in err.errors() {
+            ret = ret.with_property(key, value.clone());
+        }
+        if err.extensions().get("retryable").is_some_and(|r| r == true) {
+            ret = ret.retryable();
+        }
+        ret
+    }
+}
+
+/// Builds an error code from the status, like `BAD_GATEWAY`
+fn status_code(status: StatusCode) -> String {
+    status
+        .canonical_reason()
+        .map(|r| {
+            r.chars()
+                .filter_map(|c| match c {
+                    ' ' | '-' => Some('_'),
+                    c if c.is_ascii_alphanumeric() => Some(c.to_ascii_uppercase()),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_else(|| GenericErrorCode::InternalServerError.code().to_owned())
+}
+
+/// Layer that applies the [ApiErrorClientService] middleware to HTTP clients calling other services.
+///
+/// Unsuccessful responses are mapped into a [`Box<Error>`](Error), rebuilt from the [ApiError] body (if any), so the
+/// upstream errors can be propagated as they are.
+///
+/// When the [RequestId] is included on the outgoing request extensions, it's sent on the [REQUEST_ID_HEADER], to
+/// be reused by the other service when [trusting the header](crate::request_id::TrustedRequestIdLayer).
+///
+/// ``` rust ignore
+/// let client = ServiceBuilder::new().layer(ApiErrorClientLayer).service(hyper_client);
+///
+/// let mut req = Request::get("http://users/api/users/1").body(Body::empty())?;
+/// req.extensions_mut().insert(request_id);
+/// let res = client.oneshot(req).await?;
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ApiErrorClientLayer;
+
+impl<S> Layer<S> for ApiErrorClientLayer {
+    type Service = ApiErrorClientService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ApiErrorClientService { inner }
+    }
+}
+
+/// Middleware which maps unsuccessful responses into a [`Box<Error>`](Error).
+///
+/// See [ApiErrorClientLayer].
+#[derive(Debug, Clone)]
+pub struct ApiErrorClientService<S> {
+    inner: S,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for ApiErrorClientService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Error: Into<BoxError>,
+    S::Future: Send + 'static,
+    ResBody: HttpBody<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<BoxError>,
+{
+    type Error = Box<Error>;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+    type Response = Response<ResBody>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(upstream_unavailable)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        // Propagate the request id
+        if let Some(request_id) = req.extensions().get::<RequestId>()
+            && let Ok(value) = HeaderValue::from_str(&request_id.to_string())
+        {
+            req.headers_mut().insert(REQUEST_ID_HEADER, value);
+        }
+
+        let fut = self.inner.call(req);
+        Box::pin(async move {
+            let res = fut.await.map_err(upstream_unavailable)?;
+            if res.status().is_success() || res.status().is_informational() || res.status().is_redirection() {
+                return Ok(res);
+            }
+            Err(response_to_err(res).await)
+        })
+    }
+}
+
+/// Maps an error reaching the upstream service
+#[track_caller]
+fn upstream_unavailable(err: impl Into<BoxError>) -> Box<Error> {
+    Error::new(GenericErrorCode::ServiceUnavailable)
+        .with_reason("Couldn't reach the upstream service")
+        .with_error_source(err)
+}
+
+/// Builds the error from an unsuccessful response
+async fn response_to_err<B>(res: Response<B>) -> Box<Error>
+where
+    B: HttpBody<Data = Bytes> + Send + 'static,
+    B::Error: Into<BoxError>,
+{
+    let status = res.status();
+    let is_json = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with(super::APPLICATION_PROBLEM_JSON) || v.starts_with("application/json"));
+    let body = axum::body::to_bytes(Body::new(res.into_body()), MAX_ERROR_BODY_SIZE).await;
+    let api_err = match body {
+        Ok(body) if is_json => serde_json::from_slice::<Box<ApiError>>(&body).ok(),
+        _ => None,
+    };
+    match api_err {
+        Some(api_err) => api_err.into(),
+        None => {
+            let code = status_code(status);
+            let reason = status.canonical_reason().unwrap_or("Unknown");
+            Error::new(DynamicErrorInfo::new(status, &code, reason, HashMap::new()))
+                .with_reason(format!("Upstream service responded with status {status}"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{routing::get, Router};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::{
+        error::err,
+        request_id::{RequestId, TrustedRequestIdLayer},
+    };
+
+    #[derive(Debug, ErrorInfo)]
+    enum TestErrorCode {
+        #[error(status = StatusCode::BAD_REQUEST, message = "Malformed \"{header}\" header")]
+        MalformedHeader { header: String },
+    }
+
+    #[tokio::test]
+    async fn test_api_error_client() {
+        let upstream = Router::new()
+            .route(
+                "/",
+                get(|req: Request<Body>| async move {
+                    let request_id = req.extensions().get::<RequestId>().unwrap().to_string();
+                    Err::<(), Box<ApiError>>(
+                        err!(TestErrorCode::MalformedHeader { header: request_id }).into(),
+                    )
+                }),
+            )
+            .layer(TrustedRequestIdLayer);
+        let client = ApiErrorClientLayer.layer(upstream);
+
+        let request_id: RequestId = "01ARZ3NDEKTSV4RRFFQ69G5FAV".parse().unwrap();
+        let mut req = Request::new(Body::empty());
+        req.extensions_mut().insert(request_id);
+        let err = client.oneshot(req).await.unwrap_err();
+
+        assert_eq!(err.info().status(), StatusCode::BAD_REQUEST);
+        assert_eq!(err.info().code(), "MALFORMED_HEADER");
+        assert_eq!(
+            err.info().message(),
+            "Malformed \"01ARZ3NDEKTSV4RRFFQ69G5FAV\" header"
+        );
+        assert_eq!(err.info().raw_message(), "Malformed \"{header}\" header");
+        assert!(!err.is_unexpected());
+    }
+
+    #[test]
+    fn test_status_code() {
+        assert_eq!(status_code(StatusCode::BAD_GATEWAY), "BAD_GATEWAY");
+        assert_eq!(status_code(StatusCode::IM_A_TEAPOT), "IM_A_TEAPOT");
+    }
+}
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, fmt, sync::Arc};
+use std::{any::Any, collections::HashMap, error::Error as StdError, fmt, panic::Location, sync::Arc, time::Duration};
 
 use error_info::ErrorInfo;
 use http::StatusCode;
@@ -17,21 +17,43 @@ pub enum GenericErrorCode {
     Forbidden,
     #[error(status = StatusCode::NOT_FOUND, message = "The resource could not be found")]
     NotFound,
+    #[error(status = StatusCode::CONFLICT, message = "The request conflicts with the current state of the resource")]
+    Conflict,
+    #[error(status = StatusCode::SERVICE_UNAVAILABLE, message = "The service is temporarily unavailable")]
+    ServiceUnavailable,
     #[error(status = StatusCode::GATEWAY_TIMEOUT, message = "Timeout exceeded while waiting for a response")]
     GatewayTimeout,
     #[error(status = StatusCode::INTERNAL_SERVER_ERROR, message = "Internal server error")]
     InternalServerError,
 }
 
+/// Hint for clients about whether a failed request can be retried
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetryHint {
+    /// The minimum delay before retrying (if known)
+    pub after: Option<Duration>,
+}
+
+impl RetryHint {
+    /// Returns the delay in seconds (rounded up) before retrying, if known
+    pub fn after_secs(&self) -> Option<u64> {
+        self.after
+            .map(|after| after.as_secs() + u64::from(after.subsec_nanos() > 0))
+    }
+}
+
 /// This type represents an error in the service
 #[derive(Clone)]
 pub struct Error {
     pub(super) info: Arc<dyn ErrorInfo + Send + Sync + 'static>,
+    pub(super) info_any: Arc<dyn Any + Send + Sync + 'static>,
     pub(super) reason: Option<String>,
     pub(super) properties: Option<HashMap<String, serde_json::Value>>,
     pub(super) unexpected: bool,
-    pub(super) source: Option<Arc<dyn fmt::Display + Send + Sync>>,
+    pub(super) source: Option<Arc<dyn StdError + Send + Sync>>,
     pub(super) context: SpanTrace,
+    pub(super) location: &'static Location<'static>,
+    pub(super) retry: Option<RetryHint>,
 }
 struct ErrorInfoDebug {
     status: StatusCode,
@@ -65,24 +87,38 @@ impl fmt::Debug for Error {
             .field("properties", &self.properties)
             .field("source", &self.source.as_ref().map(|s| s.to_string()))
             .field("context", &self.context)
+            .field("location", &self.location)
+            .field("retry", &self.retry)
             .finish()
     }
 }
 impl Error {
-    /// Creates a new [`Box<Error>`](Error), which will be unexpected if the provided info has a server error status
+    /// Creates a new [`Box<Error>`](Error), which will be unexpected if the provided info has a server error status.
+    ///
+    /// Errors with `429 Too Many Requests`, `503 Service Unavailable` or `504 Gateway Timeout` status are retryable by
+    /// default.
+    #[track_caller]
     pub fn new(info: impl ErrorInfo + Send + Sync + 'static) -> Box<Self> {
         let info = Arc::new(info);
+        let retryable = matches!(
+            info.status(),
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+        );
         Box::new(Self {
+            retry: retryable.then(RetryHint::default),
             unexpected: info.status().is_server_error(),
+            info_any: info.clone(),
             info,
             reason: None,
             properties: None,
             source: None,
             context: SpanTrace::capture(),
+            location: Location::caller(),
         })
     }
 
     /// Creates a new internal server error
+    #[track_caller]
     pub fn internal(reason: impl Into<String>) -> Box<Self> {
         Self::new(GenericErrorCode::InternalServerError).with_reason(reason)
     }
@@ -105,15 +141,42 @@ impl Error {
         self
     }
 
+    /// Marks this error as retryable
+    pub fn retryable(mut self: Box<Self>) -> Box<Self> {
+        self.retry.get_or_insert_with(RetryHint::default);
+        self
+    }
+
+    /// Marks this error as retryable after the given delay
+    pub fn with_retry_after(mut self: Box<Self>, after: Duration) -> Box<Self> {
+        self.retry = Some(RetryHint { after: Some(after) });
+        self
+    }
+
+    /// Updates the retry hint of the error, [None] meaning it's not retryable
+    pub fn with_retry(mut self: Box<Self>, retry: Option<RetryHint>) -> Box<Self> {
+        self.retry = retry;
+        self
+    }
+
     /// Updates the reason of the error
     pub fn with_reason(mut self: Box<Self>, reason: impl Into<String>) -> Box<Self> {
         self.reason = Some(reason.into());
         self
     }
 
-    /// Updates the source of the error
+    /// Updates the source of the error.
+    ///
+    /// The source is only kept as a cause to be displayed, use [with_error_source](Error::with_error_source) to keep
+    /// its own chain of causes.
     pub fn with_source<S: fmt::Display + Send + Sync + 'static>(mut self: Box<Self>, source: S) -> Box<Self> {
-        self.source = Some(Arc::new(source));
+        self.source = Some(Arc::new(DisplaySource(source)));
+        self
+    }
+
+    /// Updates the source of the error, keeping its own chain of causes
+    pub fn with_error_source<S: Into<Box<dyn StdError + Send + Sync>>>(mut self: Box<Self>, source: S) -> Box<Self> {
+        self.source = Some(Arc::from(source.into()));
         self
     }
 
@@ -138,11 +201,26 @@ impl Error {
         self.info.as_ref()
     }
 
+    /// Returns the error info if it's of the given type
+    pub fn downcast_info<T: ErrorInfo + 'static>(&self) -> Option<&T> {
+        self.info_any.downcast_ref()
+    }
+
     /// Returns wether this error is unexpected or not
     pub fn is_unexpected(&self) -> bool {
         self.unexpected
     }
 
+    /// Returns the location of the source code where this error was created
+    pub fn location(&self) -> &'static Location<'static> {
+        self.location
+    }
+
+    /// Returns the retry hint, if the error is retryable
+    pub fn retry(&self) -> Option<RetryHint> {
+        self.retry
+    }
+
     /// Returns the reason (if any)
     pub fn reason(&self) -> Option<&str> {
         self.reason.as_deref()
@@ -153,6 +231,22 @@ impl Error {
         self.properties.as_ref()
     }
 
+    /// Returns an iterator over the chain of causes of this error, starting with its direct source
+    pub fn causes(&self) -> impl Iterator<Item = &(dyn StdError + 'static)> {
+        std::iter::successors(StdError::source(self), |&cause| cause.source())
+    }
+
+    /// Returns the first cause on the chain of the given type (if any)
+    pub fn find_cause<T: StdError + 'static>(&self) -> Option<&T> {
+        self.causes().find_map(|cause| cause.downcast_ref::<T>())
+    }
+
+    /// Overrides the location where the error was created, when it can't be tracked by the caller
+    pub(crate) fn located(mut self: Box<Self>, location: &'static Location<'static>) -> Box<Self> {
+        self.location = location;
+        self
+    }
+
     /// Returns the reason if any or the default error code message otherwise
     pub(super) fn reason_or_message(&self) -> String {
         self.reason.clone().unwrap_or(self.info.message())
@@ -171,8 +265,8 @@ impl fmt::Display for Error {
             self.reason_or_message()
         )?;
         if f.alternate() {
-            if let Some(source) = &self.source {
-                write!(f, "\nCaused by: {source}")?;
+            for cause in self.causes() {
+                write!(f, "\nCaused by: {cause}")?;
             }
             write!(f, "\n{}", self.context)
         } else {
@@ -181,6 +275,69 @@ impl fmt::Display for Error {
     }
 }
 
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.source.as_deref().map(|s| s as _)
+    }
+}
+
+/// Wrapper to use a value only implementing [Display](fmt::Display) as the source of an [Error]
+struct DisplaySource<D>(D);
+
+impl<D: fmt::Display> fmt::Debug for DisplaySource<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"{}\"", self.0)
+    }
+}
+
+impl<D: fmt::Display> fmt::Display for DisplaySource<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<D: fmt::Display> StdError for DisplaySource<D> {}
+
+/// Converts an [anyhow::Error] into a [`Box<Error>`](Error).
+///
+/// If it was built from a [`Box<Error>`](Error), it's recovered as is. When some context was added on top of it, the
+/// error info is kept but the whole [anyhow::Error] chain becomes its source, so the context is not lost. Otherwise,
+/// an internal server error is returned with the [anyhow::Error] as its source.
+///
+/// The opposite conversion is already provided by [anyhow], as [Error] implements [std::error::Error]:
+///
+/// ```
+/// # use graphql_starter::{err, error::{Error, GenericErrorCode}};
+/// let err: anyhow::Error = err!(GenericErrorCode::NotFound).into();
+/// let err: Box<Error> = err.into();
+/// assert_eq!(err.info().code(), "NOT_FOUND");
+/// ```
+impl From<anyhow::Error> for Box<Error> {
+    #[track_caller]
+    fn from(err: anyhow::Error) -> Self {
+        let inner = err
+            .downcast_ref::<Box<Error>>()
+            .map(|e| &**e)
+            .or_else(|| err.downcast_ref::<Error>());
+        let Some(inner) = inner else {
+            return Error::new(GenericErrorCode::InternalServerError).with_error_source(err);
+        };
+        // Recover the error only if there's no context on top of it
+        let outer = err.chain().next();
+        if outer.is_some_and(|e| e.is::<Box<Error>>() || e.is::<Error>()) {
+            match err.downcast::<Box<Error>>() {
+                Ok(err) => err,
+                Err(err) => match err.downcast::<Error>() {
+                    Ok(err) => Box::new(err),
+                    Err(err) => Error::new(GenericErrorCode::InternalServerError).with_error_source(err),
+                },
+            }
+        } else {
+            Box::new(inner.clone()).with_error_source(err)
+        }
+    }
+}
+
 /// Creates a new [`Box<Error>`](Error), which will be unexpected if the provided info has a server error status.
 ///
 /// # Examples
@@ -233,16 +390,27 @@ pub trait MapToErr<T> {
     fn map_to_err_with(self, code: impl ErrorInfo + Send + Sync + 'static, reason: &'static str) -> Result<T>;
 }
 impl<T, E: fmt::Display + Send + Sync + 'static> MapToErr<T> for Result<T, E> {
+    #[track_caller]
     fn map_to_internal_err(self, reason: &'static str) -> Result<T> {
-        self.map_err(|source| Error::internal(reason).with_source(source))
+        let location = Location::caller();
+        self.map_err(|source| Error::internal(reason).with_source(source).located(location))
     }
 
+    #[track_caller]
     fn map_to_err(self, code: impl ErrorInfo + Send + Sync + 'static) -> Result<T> {
-        self.map_err(|source| Error::new(code).with_source(source))
+        let location = Location::caller();
+        self.map_err(|source| Error::new(code).with_source(source).located(location))
     }
 
+    #[track_caller]
     fn map_to_err_with(self, code: impl ErrorInfo + Send + Sync + 'static, reason: &'static str) -> Result<T> {
-        self.map_err(|source| Error::new(code).with_reason(reason).with_source(source))
+        let location = Location::caller();
+        self.map_err(|source| {
+            Error::new(code)
+                .with_reason(reason)
+                .with_source(source)
+                .located(location)
+        })
     }
 }
 
@@ -256,16 +424,22 @@ pub trait OkOrErr<T> {
     fn ok_or_err_with(self, code: impl ErrorInfo + Send + Sync + 'static, reason: &'static str) -> Result<T>;
 }
 impl<T> OkOrErr<T> for Option<T> {
+    #[track_caller]
     fn ok_or_internal_err(self, reason: &'static str) -> Result<T> {
-        self.ok_or_else(|| Error::internal(reason))
+        let location = Location::caller();
+        self.ok_or_else(|| Error::internal(reason).located(location))
     }
 
+    #[track_caller]
     fn ok_or_err(self, code: impl ErrorInfo + Send + Sync + 'static) -> Result<T> {
-        self.ok_or_else(|| Error::new(code))
+        let location = Location::caller();
+        self.ok_or_else(|| Error::new(code).located(location))
     }
 
+    #[track_caller]
     fn ok_or_err_with(self, code: impl ErrorInfo + Send + Sync + 'static, reason: &'static str) -> Result<T> {
-        self.ok_or_else(|| Error::new(code).with_reason(reason))
+        let location = Location::caller();
+        self.ok_or_else(|| Error::new(code).with_reason(reason).located(location))
     }
 }
 
@@ -277,6 +451,8 @@ pub trait ResultExt {
     fn expected(self) -> Self;
     /// Appends an string property to the error side of the result
     fn with_str_property(self, key: &'static str, value: &'static str) -> Self;
+    /// Marks the error side of the result as retryable
+    fn retryable(self) -> Self;
 }
 impl<T> ResultExt for Result<T> {
     fn unexpected(self) -> Self {
@@ -290,4 +466,68 @@ impl<T> ResultExt for Result<T> {
     fn with_str_property(self, key: &'static str, value: &'static str) -> Self {
         self.map_err(|err| err.with_str_property(key, value))
     }
+
+    fn retryable(self) -> Self {
+        self.map_err(|err| err.retryable())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::*;
+
+    #[test]
+    fn test_cause_chain() {
+        let io_err = io::Error::new(io::ErrorKind::NotFound, "file missing");
+        let anyhow_err = anyhow::Error::new(io_err).context("couldn't read config");
+        let err = err!(GenericErrorCode::NotFound).with_error_source(anyhow_err);
+
+        let causes = err.causes().map(|c| c.to_string()).collect::<Vec<_>>();
+        assert_eq!(causes, vec!["couldn't read config", "file missing"]);
+        assert_eq!(err.find_cause::<io::Error>().unwrap().kind(), io::ErrorKind::NotFound);
+
+        let display = format!("{err:#}");
+        assert!(display.contains("\nCaused by: couldn't read config\nCaused by: file missing"));
+    }
+
+    #[test]
+    fn test_retry_hint() {
+        assert_eq!(err!(GenericErrorCode::GatewayTimeout).retry(), Some(RetryHint::default()));
+        assert_eq!(err!(GenericErrorCode::BadRequest).retry(), None);
+
+        let err = err!("Database is busy").with_retry_after(Duration::from_millis(1500));
+        assert_eq!(err.retry().and_then(|r| r.after_secs()), Some(2));
+
+        let api_err = crate::error::ApiError::from_err(err);
+        let headers = api_err.headers().as_ref().unwrap();
+        assert_eq!(headers[http::header::RETRY_AFTER], "2");
+        assert_eq!(api_err.extensions()["retryable"], true);
+    }
+
+    #[test]
+    fn test_anyhow_interop() {
+        let err: anyhow::Error = err!(GenericErrorCode::Forbidden, "Not allowed").into();
+        let err: Box<Error> = err.into();
+        assert_eq!(err.info().code(), "FORBIDDEN");
+        assert_eq!(err.reason(), Some("Not allowed"));
+        assert!(err.causes().next().is_none());
+
+        // The context is kept as the source
+        let err: anyhow::Error = err!(GenericErrorCode::Forbidden, "Not allowed").into();
+        let err: Box<Error> = err.context("wrapped").into();
+        assert_eq!(err.info().code(), "FORBIDDEN");
+        assert_eq!(err.reason(), Some("Not allowed"));
+        assert_eq!(err.causes().next().unwrap().to_string(), "wrapped");
+
+        // Display-only sources are kept as causes too
+        let err = err!("Couldn't process").with_source("rejected");
+        assert_eq!(err.causes().next().unwrap().to_string(), "rejected");
+
+        let err: Box<Error> = anyhow::anyhow!("unknown").into();
+        assert_eq!(err.info().code(), "INTERNAL_SERVER_ERROR");
+        assert!(err.is_unexpected());
+        assert_eq!(err.causes().next().unwrap().to_string(), "unknown");
+    }
 }
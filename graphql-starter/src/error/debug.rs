@@ -0,0 +1,101 @@
+//! Debug mode for errors, exposing their internals to trusted callers.
+//!
+//! When enabled, the reason, the chain of causes and the span trace of the errors are included on the `debug` entry
+//! of the [ApiError](super::ApiError) `errors` or the GraphQL error extensions.
+//!
+//! It can be enabled globally, with [ApiErrorLayer::with_debug](super::ApiErrorLayer::with_debug) and
+//! [GraphQLErrorExtension::with_debug](crate::graphql::GraphQLErrorExtension::with_debug), or per request by
+//! including the [ErrorDebug] marker on the request extensions (see `error_debug_middleware`).
+
+use serde_json::json;
+
+use super::Error;
+
+/// Name of the header used by callers to request error internals
+pub const ERROR_DEBUG_HEADER: &str = "x-error-debug";
+
+/// Marker to enable the debug mode for the errors of a request.
+///
+/// It's looked up on the request and response extensions for REST handlers and on the context data for GraphQL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorDebug;
+
+/// Builds the debug details of an error
+pub(crate) fn debug_details(err: &Error) -> serde_json::Value {
+    json!({
+        "reason": err.reason(),
+        "causes": err.causes().map(|c| c.to_string()).collect::<Vec<_>>(),
+        "spanTrace": err.context.to_string(),
+    })
+}
+
+#[cfg(feature = "auth")]
+pub use self::auth::*;
+
+#[cfg(feature = "auth")]
+mod auth {
+    use axum::{
+        extract::{OptionalFromRequestParts, Request, State},
+        middleware::Next,
+        response::Response,
+    };
+
+    use super::{ErrorDebug, ERROR_DEBUG_HEADER};
+    use crate::auth::{Auth, AuthState, AuthorizationService, Subject};
+
+    /// Relation checked against the [AuthorizationService] to enable the debug mode
+    pub const ERROR_DEBUG_RELATION: &str = "debug";
+
+    /// Object checked against the [AuthorizationService] to enable the debug mode
+    pub const ERROR_DEBUG_OBJECT: &str = "errors";
+
+    /// Middleware that enables the debug mode for requests including the [ERROR_DEBUG_HEADER], as long as the
+    /// authenticated subject is allowed to [ERROR_DEBUG_RELATION] the [ERROR_DEBUG_OBJECT].
+    ///
+    /// Requests not allowed are processed as usual, without failing.
+    ///
+    /// ``` rust ignore
+    /// let router = Router::new()
+    ///     .route("/", get(handler))
+    ///     .layer(middleware::from_fn_with_state(state.clone(), error_debug_middleware::<Subject, AppState>))
+    ///     .layer(ApiErrorLayer::new());
+    /// ```
+    pub async fn error_debug_middleware<S, St>(State(state): State<St>, req: Request, next: Next) -> Response
+    where
+        S: Subject,
+        St: AuthState<S>,
+    {
+        if !req.headers().contains_key(ERROR_DEBUG_HEADER) {
+            return next.run(req).await;
+        }
+
+        // Authenticate and authorize the subject
+        let (mut parts, body) = req.into_parts();
+        let debug = match <Auth<S> as OptionalFromRequestParts<St>>::from_request_parts(&mut parts, &state).await {
+            Ok(Some(Auth(subject))) => {
+                match state
+                    .authz()
+                    .authorize(&subject, ERROR_DEBUG_RELATION, ERROR_DEBUG_OBJECT)
+                    .await
+                {
+                    Ok(()) => true,
+                    Err(_) => {
+                        tracing::debug!("Subject {subject} is not allowed to debug errors");
+                        false
+                    }
+                }
+            }
+            _ => false,
+        };
+        let mut req = Request::from_parts(parts, body);
+
+        if debug {
+            req.extensions_mut().insert(ErrorDebug);
+            let mut res = next.run(req).await;
+            res.extensions_mut().insert(ErrorDebug);
+            res
+        } else {
+            next.run(req).await
+        }
+    }
+}
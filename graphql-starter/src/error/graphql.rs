@@ -1,11 +1,11 @@
-use std::{any::Any, sync::Arc};
+use std::{any::Any, error::Error as StdError, sync::Arc};
 
 use async_graphql::{ErrorExtensions, Name};
 use error_info::ErrorInfo;
 use indexmap::IndexMap;
 use tracing_error::SpanTrace;
 
-use super::{Error, GenericErrorCode};
+use super::{Error, GenericErrorCode, RetryHint};
 
 /// GraphQL Result that represents either success ([`Ok`]) or failure ([`Err`])
 pub type GraphQLResult<T, E = Box<GraphQLError>> = std::result::Result<T, E>;
@@ -19,11 +19,13 @@ pub enum GraphQLError {
 
 impl GraphQLError {
     /// Creates a new [GraphQLError]
+    #[track_caller]
     pub fn new(info: impl ErrorInfo + Send + Sync + 'static) -> Box<Self> {
         Box::new(Self::Custom(Error::new(info)))
     }
 
     /// Creates a new internal server error
+    #[track_caller]
     pub fn internal(reason: impl Into<String>) -> Box<Self> {
         Box::new(Self::Custom(Error::internal(reason)))
     }
@@ -107,6 +109,11 @@ impl From<Box<Error>> for Box<GraphQLError> {
     }
 }
 
+/// Converts the error into an [async_graphql::Error].
+///
+/// The source of the converted error is always the core [Error], not its original cause, so it can be inspected later
+/// by the [GraphQLErrorExtension](crate::graphql::GraphQLErrorExtension). The original cause can be retrieved with
+/// [GraphQLErrorSource::find_cause].
 impl From<Box<GraphQLError>> for async_graphql::Error {
     fn from(value: Box<GraphQLError>) -> Self {
         let e = *value;
@@ -130,28 +137,31 @@ impl From<Box<GraphQLError>> for async_graphql::Error {
             }
         }
 
-        // Convert type
-        let (gql_err, err_info): (async_graphql::Error, Option<Arc<dyn ErrorInfo + Send + Sync + 'static>>) = match e {
-            GraphQLError::Async(mut err, _) => {
+        // Convert type, keeping the core error as the source so it can be inspected later (see debug mode)
+        let (gql_err, err_info, retry): (
+            async_graphql::Error,
+            Option<Arc<dyn ErrorInfo + Send + Sync + 'static>>,
+            Option<RetryHint>,
+        ) = match e {
+            GraphQLError::Async(mut err, context) => {
                 if new_error {
                     // Hide the message and provide generic internal error info
-                    err.source = Some(Arc::new(err.message));
+                    let mut source = Error::internal(err.message);
+                    source.context = context;
+                    err.source = Some(Arc::new(*source));
                     err.message = GenericErrorCode::InternalServerError.raw_message().into();
-                    (err, Some(Arc::new(GenericErrorCode::InternalServerError)))
+                    (err, Some(Arc::new(GenericErrorCode::InternalServerError)), None)
                 } else {
                     // Already converted
-                    (err, None)
+                    (err, None, None)
                 }
             }
             GraphQLError::Custom(err) => {
                 let err = *err;
-                let source = err.source.map(|s| {
-                    let source: Arc<dyn Any + Send + Sync> = Arc::new(s);
-                    source
-                });
+                let source: Arc<dyn Any + Send + Sync> = Arc::new(err.clone());
                 let async_err = async_graphql::Error {
                     message: err.info.message(),
-                    source,
+                    source: Some(source),
                     extensions: None,
                 }
                 .extend_with(|_, e| {
@@ -162,6 +172,8 @@ impl From<Box<GraphQLError>> for async_graphql::Error {
                                 || k == "errorCode"
                                 || k == "rawMessage"
                                 || k == "messageFields"
+                                || k == "retryable"
+                                || k == "retryAfter"
                             {
                                 tracing::error!("Error '{}' contains a reserved property: {}", err.info.code(), k);
                                 continue;
@@ -173,7 +185,7 @@ impl From<Box<GraphQLError>> for async_graphql::Error {
                         }
                     }
                 });
-                (async_err, Some(err.info))
+                (async_err, Some(err.info), err.retry)
             }
         };
         if let Some(err_info) = err_info {
@@ -191,9 +203,57 @@ impl From<Box<GraphQLError>> for async_graphql::Error {
                     let fields_map = IndexMap::from_iter(fields.into_iter().map(|(k, v)| (Name::new(k), v.into())));
                     e.set("messageFields", fields_map);
                 }
+                if let Some(retry) = retry {
+                    e.set("retryable", true);
+                    if let Some(after) = retry.after_secs() {
+                        e.set("retryAfter", after);
+                    }
+                }
             })
         } else {
             gql_err
         }
     }
 }
+
+/// Utility trait to inspect the core [Error] kept as the source of converted GraphQL errors
+pub trait GraphQLErrorSource {
+    /// Retrieves the core [Error] this error was converted from (if any)
+    fn core_error(&self) -> Option<&Error>;
+
+    /// Returns the first cause on the chain of the core error of the given type (if any)
+    fn find_cause<T: StdError + 'static>(&self) -> Option<&T> {
+        self.core_error().and_then(|err| err.find_cause())
+    }
+}
+
+impl GraphQLErrorSource for async_graphql::Error {
+    fn core_error(&self) -> Option<&Error> {
+        self.source.as_ref().and_then(|s| s.downcast_ref())
+    }
+}
+
+impl GraphQLErrorSource for async_graphql::ServerError {
+    fn core_error(&self) -> Option<&Error> {
+        self.source()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::*;
+
+    #[test]
+    fn test_graphql_error_source() {
+        let io_err = io::Error::new(io::ErrorKind::NotFound, "file missing");
+        let err = Error::new(GenericErrorCode::NotFound).with_error_source(io_err);
+        let gql_err: async_graphql::Error = GraphQLError::from_err(err).into();
+
+        assert_eq!(gql_err.core_error().unwrap().info().code(), "NOT_FOUND");
+        assert_eq!(gql_err.find_cause::<io::Error>().unwrap().kind(), io::ErrorKind::NotFound);
+        let server_err = gql_err.into_server_error(Default::default());
+        assert!(server_err.find_cause::<io::Error>().is_some());
+    }
+}
@@ -0,0 +1,279 @@
+//! Localization of error messages based on the `Accept-Language` header.
+//!
+//! Messages are keyed by error code and can contain the error fields as variables. When there's no message for any of
+//! the accepted languages, the default [ErrorInfo](error_info::ErrorInfo) message is kept.
+
+use std::collections::HashMap;
+
+use auto_impl::auto_impl;
+
+use crate::axum::extract::AcceptLanguage;
+
+/// Catalog of localized error messages
+#[auto_impl(Box, Arc)]
+pub trait ErrorMessages: Send + Sync + 'static {
+    /// Retrieves the message for the error code in the given language, with the fields interpolated.
+    ///
+    /// It returns [None] if there's no message for the code in that language.
+    fn message(&self, language: &str, code: &str, fields: &HashMap<String, String>) -> Option<String>;
+
+    /// Retrieves the message for the error code in the preferred accepted language available.
+    ///
+    /// For every accepted language, ordered by quality, both the full tag (`es-ES`) and the primary language (`es`)
+    /// are tried.
+    fn localize(
+        &self,
+        accept_language: &AcceptLanguage,
+        code: &str,
+        fields: &HashMap<String, String>,
+    ) -> Option<String> {
+        accept_language
+            .accepted_languages()?
+            .iter()
+            .filter(|l| l.as_str() != "*")
+            .find_map(|language| {
+                self.message(language, code, fields).or_else(|| {
+                    language
+                        .split_once('-')
+                        .and_then(|(primary, _)| self.message(primary, code, fields))
+                })
+            })
+    }
+}
+
+#[cfg(feature = "i18n")]
+pub use self::toml::TomlErrorMessages;
+
+#[cfg(feature = "i18n")]
+mod toml {
+    use std::{collections::HashMap, path::Path};
+
+    use anyhow::{Context, Result};
+
+    use super::{read_dir, ErrorMessages};
+    use crate::error::interpolate;
+
+    /// [ErrorMessages] loaded from TOML files.
+    ///
+    /// Each language contains a flat table with the message template for every error code, where the fields are
+    /// referenced the same way than on the `ErrorInfo` messages:
+    ///
+    /// ``` toml
+    /// NOT_FOUND = "No se ha encontrado el recurso"
+    /// PAGE_EXCEEDS_LIMIT = "El parámetro \"{field}\" no puede exceder {max}"
+    /// ```
+    #[derive(Debug, Clone, Default)]
+    pub struct TomlErrorMessages {
+        languages: HashMap<String, HashMap<String, String>>,
+    }
+
+    impl TomlErrorMessages {
+        /// Creates a new empty catalog
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Reads every `{language}.toml` file on the given folder
+        pub fn from_dir(path: impl AsRef<Path>) -> Result<Self> {
+            let mut ret = Self::new();
+            for (language, content) in read_dir(path.as_ref(), "toml")? {
+                ret = ret.with_language(language, &content)?;
+            }
+            Ok(ret)
+        }
+
+        /// Appends the messages for a language from the TOML content, overriding existing ones
+        pub fn with_language(mut self, language: impl AsRef<str>, content: &str) -> Result<Self> {
+            let language = language.as_ref();
+            let messages: HashMap<String, String> =
+                ::toml::from_str(content).with_context(|| format!("Couldn't parse '{language}' error messages"))?;
+            self.languages
+                .entry(language.to_lowercase())
+                .or_default()
+                .extend(messages);
+            Ok(self)
+        }
+    }
+
+    impl ErrorMessages for TomlErrorMessages {
+        fn message(&self, language: &str, code: &str, fields: &HashMap<String, String>) -> Option<String> {
+            let template = self.languages.get(&language.to_lowercase())?.get(code)?;
+            Some(interpolate(template, |name| fields.get(name)))
+        }
+    }
+}
+
+#[cfg(feature = "fluent")]
+pub use self::fluent::FluentErrorMessages;
+
+#[cfg(feature = "fluent")]
+mod fluent {
+    use std::{
+        collections::{hash_map::Entry, HashMap},
+        path::Path,
+    };
+
+    use anyhow::{anyhow, Context, Result};
+    use fluent_bundle::{concurrent::FluentBundle, FluentArgs, FluentResource};
+    use unic_langid::LanguageIdentifier;
+
+    use super::{read_dir, ErrorMessages};
+
+    /// [ErrorMessages] loaded from [Fluent](https://projectfluent.org) files.
+    ///
+    /// Each message id must be the error code, and the fields are available as variables:
+    ///
+    /// ``` ftl
+    /// NOT_FOUND = No se ha encontrado el recurso
+    /// PAGE_EXCEEDS_LIMIT = El parámetro "{ $field }" no puede exceder { $max }
+    /// ```
+    #[derive(Default)]
+    pub struct FluentErrorMessages {
+        languages: HashMap<String, FluentBundle<FluentResource>>,
+    }
+
+    impl FluentErrorMessages {
+        /// Creates a new empty catalog
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Reads every `{language}.ftl` file on the given folder
+        pub fn from_dir(path: impl AsRef<Path>) -> Result<Self> {
+            let mut ret = Self::new();
+            for (language, content) in read_dir(path.as_ref(), "ftl")? {
+                ret = ret.with_language(language, content)?;
+            }
+            Ok(ret)
+        }
+
+        /// Appends the messages for a language from the Fluent content, failing if some message already exists
+        pub fn with_language(mut self, language: impl AsRef<str>, content: impl Into<String>) -> Result<Self> {
+            let language = language.as_ref();
+            let resource = FluentResource::try_new(content.into())
+                .map_err(|(_, errs)| anyhow!("Couldn't parse '{language}' error messages: {errs:?}"))?;
+            let bundle = match self.languages.entry(language.to_lowercase()) {
+                Entry::Occupied(e) => e.into_mut(),
+                Entry::Vacant(e) => {
+                    let id: LanguageIdentifier = language
+                        .parse()
+                        .with_context(|| format!("Invalid language identifier: {language}"))?;
+                    let mut bundle = FluentBundle::new_concurrent(vec![id]);
+                    // Avoid unicode isolation marks around variables, messages are rendered on their own
+                    bundle.set_use_isolating(false);
+                    e.insert(bundle)
+                }
+            };
+            bundle
+                .add_resource(resource)
+                .map_err(|errs| anyhow!("Couldn't add '{language}' error messages: {errs:?}"))?;
+            Ok(self)
+        }
+    }
+
+    impl ErrorMessages for FluentErrorMessages {
+        fn message(&self, language: &str, code: &str, fields: &HashMap<String, String>) -> Option<String> {
+            let bundle = self.languages.get(&language.to_lowercase())?;
+            let pattern = bundle.get_message(code)?.value()?;
+            let mut args = FluentArgs::with_capacity(fields.len());
+            for (key, value) in fields {
+                args.set(key.as_str(), value.as_str());
+            }
+            let mut errors = Vec::new();
+            let message = bundle.format_pattern(pattern, Some(&args), &mut errors);
+            if !errors.is_empty() {
+                tracing::error!("Couldn't format '{code}' error message for '{language}': {errors:?}");
+            }
+            Some(message.into_owned())
+        }
+    }
+}
+
+/// Reads every file with the given extension on the folder, returning the file stem along with its content
+#[cfg(feature = "i18n")]
+fn read_dir(path: &std::path::Path, extension: &str) -> anyhow::Result<Vec<(String, String)>> {
+    use anyhow::Context;
+
+    let mut ret = Vec::new();
+    let entries = std::fs::read_dir(path).with_context(|| format!("Couldn't read error messages folder: {path:?}"))?;
+    for entry in entries {
+        let path = entry.context("Couldn't read error messages folder entry")?.path();
+        if path.extension().is_some_and(|e| e == extension)
+            && let Some(language) = path.file_stem().and_then(|s| s.to_str())
+        {
+            let content = std::fs::read_to_string(&path).with_context(|| format!("Couldn't read {path:?}"))?;
+            ret.push((language.to_owned(), content));
+        }
+    }
+    Ok(ret)
+}
+
+#[cfg(all(test, feature = "i18n"))]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    fn accept_language(header: &str) -> AcceptLanguage {
+        AcceptLanguage(Some(Arc::new(accept_language::parse(header))))
+    }
+
+    fn fields() -> HashMap<String, String> {
+        HashMap::from([("field".into(), "first".into()), ("max".into(), "10".into())])
+    }
+
+    #[test]
+    fn test_toml_messages() {
+        let messages = TomlErrorMessages::new()
+            .with_language(
+                "es",
+                r#"
+                    NOT_FOUND = "No se ha encontrado el recurso"
+                    PAGE_EXCEEDS_LIMIT = "El parámetro \"{field}\" no puede exceder {max}"
+                "#,
+            )
+            .unwrap()
+            .with_language("fr-CA", r#"NOT_FOUND = "Ressource introuvable""#)
+            .unwrap();
+
+        assert_eq!(
+            messages.localize(&accept_language("es-ES,en;q=0.5"), "PAGE_EXCEEDS_LIMIT", &fields()),
+            Some("El parámetro \"first\" no puede exceder 10".into())
+        );
+        assert_eq!(
+            messages.localize(&accept_language("fr-CA,es;q=0.5"), "NOT_FOUND", &HashMap::new()),
+            Some("Ressource introuvable".into())
+        );
+        assert_eq!(
+            messages.localize(&accept_language("fr-CA,es;q=0.5"), "PAGE_EXCEEDS_LIMIT", &fields()),
+            Some("El parámetro \"first\" no puede exceder 10".into())
+        );
+        assert_eq!(
+            messages.localize(&accept_language("de"), "NOT_FOUND", &HashMap::new()),
+            None
+        );
+        // Field values are never parsed as placeholders
+        let fields = HashMap::from([("field".into(), "{max}".into()), ("max".into(), "10".into())]);
+        assert_eq!(
+            messages.localize(&accept_language("es"), "PAGE_EXCEEDS_LIMIT", &fields),
+            Some("El parámetro \"{max}\" no puede exceder 10".into())
+        );
+        assert_eq!(
+            messages.localize(&AcceptLanguage(None), "NOT_FOUND", &HashMap::new()),
+            None
+        );
+    }
+
+    #[cfg(feature = "fluent")]
+    #[test]
+    fn test_fluent_messages() {
+        let messages = FluentErrorMessages::new()
+            .with_language("es", "PAGE_EXCEEDS_LIMIT = El parámetro \"{ $field }\" no puede exceder { $max }")
+            .unwrap();
+
+        assert_eq!(
+            messages.localize(&accept_language("es-ES"), "PAGE_EXCEEDS_LIMIT", &fields()),
+            Some("El parámetro \"first\" no puede exceder 10".into())
+        );
+    }
+}
@@ -0,0 +1,55 @@
+/// Replaces the `{name}` placeholders of the template with the given values.
+///
+/// The template is filled in a single pass, so the values are never parsed as placeholders. Placeholders without a
+/// value are kept as they are.
+pub(crate) fn interpolate<V: AsRef<str>>(template: &str, value: impl Fn(&str) -> Option<V>) -> String {
+    interpolate_with(template, value, |buf, value| buf.push_str(value))
+}
+
+/// Same as [interpolate], but pushing the values into the buffer with the given function (i.e. to escape them)
+pub(crate) fn interpolate_with<V: AsRef<str>>(
+    template: &str,
+    value: impl Fn(&str) -> Option<V>,
+    push: impl Fn(&mut String, &str),
+) -> String {
+    let mut ret = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        ret.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let placeholder = rest.find('}').and_then(|end| Some((end, value(&rest[1..end])?)));
+        match placeholder {
+            Some((end, value)) => {
+                push(&mut ret, value.as_ref());
+                rest = &rest[end + 1..];
+            }
+            None => {
+                ret.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    ret.push_str(rest);
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interpolate() {
+        let value = |name: &str| match name {
+            "a" => Some("{b}"),
+            "b" => Some("2"),
+            _ => None,
+        };
+        assert_eq!(interpolate("{a} and {b}", value), "{b} and 2");
+        assert_eq!(interpolate("p {color: red} {unknown} {b", value), "p {color: red} {unknown} {b");
+        assert_eq!(interpolate("{{b}}", value), "{2}");
+        assert_eq!(
+            interpolate_with("<{a}>", value, |buf, v| buf.push_str(&v.replace('{', "&#123;"))),
+            "<&#123;b}>"
+        );
+    }
+}
@@ -0,0 +1,397 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+};
+
+use axum::{
+    extract::MatchedPath,
+    response::{IntoResponse, Response},
+};
+use http::{header, HeaderValue, Request};
+use pin_project_lite::pin_project;
+use tower::{Layer, Service};
+
+use super::{
+    debug_details, ApiError, ErrorDebug, ErrorFormat, ErrorMessages, ErrorReport, ErrorReporter, ErrorTemplate,
+    HtmlErrorTemplate, ReportedSubject,
+};
+use crate::{axum::extract::AcceptLanguage, request_id::RequestId};
+
+/// Layer that applies the [ApiErrorService] middleware, which post-processes [ApiError] responses with information
+/// from the request they're responding to.
+///
+/// When the [RequestId] is available on the request, it's used as the problem `instance`, so
+/// [RequestIdLayer](crate::request_id::RequestIdLayer) must be applied before this layer.
+///
+/// It must be added to the router, as it applies to any handler returning an [ApiError]:
+///
+/// ``` rust ignore
+/// let router = Router::new()
+///     .route("/", get(handler))
+///     .layer(
+///         ApiErrorLayer::new()
+///             .with_problem_type_base("https://example.com/problems")
+///             .with_content_negotiation()
+///             .with_messages(TomlErrorMessages::from_dir("./i18n")?),
+///     );
+/// ```
+#[derive(Clone, Default)]
+pub struct ApiErrorLayer {
+    config: ApiErrorConfig,
+}
+
+#[derive(Clone, Default)]
+struct ApiErrorConfig {
+    debug: bool,
+    problem_type_base: Option<String>,
+    messages: Option<Arc<dyn ErrorMessages>>,
+    reporter: Option<Arc<dyn ErrorReporter>>,
+    template: Option<Arc<dyn ErrorTemplate>>,
+}
+
+impl ApiErrorLayer {
+    /// Creates a new [ApiErrorLayer], which doesn't modify errors until configured
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Populates the problem `type` of errors built from an `ErrorInfo` with a URI, based on the error code.
+    ///
+    /// For example, given `https://example.com/problems` as the base, errors with `NOT_FOUND` code will have
+    /// `https://example.com/problems/not-found` type.
+    pub fn with_problem_type_base(mut self, base_uri: impl Into<String>) -> Self {
+        let mut base_uri = base_uri.into();
+        if !base_uri.ends_with('/') {
+            base_uri.push('/');
+        }
+        self.config.problem_type_base = Some(base_uri);
+        self
+    }
+
+    /// Localizes the error details to the preferred language from the `Accept-Language` header
+    pub fn with_messages(mut self, messages: impl ErrorMessages) -> Self {
+        self.config.messages = Some(Arc::new(messages));
+        self
+    }
+
+    /// Reports the unexpected errors to the given [ErrorReporter], along with the request information
+    pub fn with_reporter(mut self, reporter: impl ErrorReporter) -> Self {
+        self.config.reporter = Some(Arc::new(reporter));
+        self
+    }
+
+    /// Renders the errors on the format preferred by the `Accept` header: HTML pages for browsers, plain text for CLI
+    /// clients and JSON by default.
+    ///
+    /// HTML pages are rendered with the default [HtmlErrorTemplate], see [with_html_template](Self::with_html_template)
+    /// to override it.
+    pub fn with_content_negotiation(mut self) -> Self {
+        if self.config.template.is_none() {
+            self.config.template = Some(Arc::new(HtmlErrorTemplate::default()));
+        }
+        self
+    }
+
+    /// Enables the [content negotiation](Self::with_content_negotiation), rendering HTML pages with the given template
+    pub fn with_html_template(mut self, template: impl ErrorTemplate) -> Self {
+        self.config.template = Some(Arc::new(template));
+        self
+    }
+
+    /// Enables the [debug mode](super::ErrorDebug) for every request, it must only be enabled on development environments.
+    ///
+    /// When disabled, it can still be enabled per request with the [ErrorDebug] extension.
+    pub fn with_debug(mut self, debug: bool) -> Self {
+        self.config.debug = debug;
+        self
+    }
+}
+
+impl<S> Layer<S> for ApiErrorLayer {
+    type Service = ApiErrorService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ApiErrorService {
+            inner,
+            config: Arc::new(self.config.clone()),
+        }
+    }
+}
+
+/// Middleware which post-processes [ApiError] responses.
+///
+/// See [ApiErrorLayer].
+#[derive(Clone)]
+pub struct ApiErrorService<S> {
+    inner: S,
+    config: Arc<ApiErrorConfig>,
+}
+
+impl<S, ReqBody> Service<Request<ReqBody>> for ApiErrorService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response>,
+{
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+    type Response = S::Response;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        // Prepare the request information for the reporter
+        let report = self.config.reporter.is_some().then(|| {
+            let subject = ReportedSubject::default();
+            req.extensions_mut().insert(subject.clone());
+            let path = req
+                .extensions()
+                .get::<MatchedPath>()
+                .map(|p| p.as_str())
+                .unwrap_or(req.uri().path());
+            let operation = format!("{} {path}", req.method());
+            (subject, operation)
+        });
+        let ctx = RequestContext {
+            report,
+            debug: req.extensions().get::<ErrorDebug>().is_some(),
+            request_id: req.extensions().get::<RequestId>().copied(),
+            accept_language: AcceptLanguage::from_headers(req.headers()),
+            format: if self.config.template.is_some() {
+                ErrorFormat::from_headers(req.headers())
+            } else {
+                ErrorFormat::Json
+            },
+        };
+        ResponseFuture {
+            inner: self.inner.call(req),
+            config: self.config.clone(),
+            ctx: Some(ctx),
+        }
+    }
+}
+
+/// Request information needed to post-process the error
+struct RequestContext {
+    report: Option<(ReportedSubject, String)>,
+    debug: bool,
+    request_id: Option<RequestId>,
+    accept_language: AcceptLanguage,
+    format: ErrorFormat,
+}
+
+pin_project! {
+    /// Response future for [ApiErrorService].
+    pub struct ResponseFuture<F> {
+        #[pin]
+        inner: F,
+        config: Arc<ApiErrorConfig>,
+        ctx: Option<RequestContext>,
+    }
+}
+
+impl<F, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response, E>>,
+{
+    type Output = Result<Response, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = ready!(this.inner.poll(cx))?;
+        let ctx = this.ctx.take().expect("polled after completion");
+        Poll::Ready(Ok(process(this.config, ctx, res)))
+    }
+}
+
+/// Post-processes the response if it contains an [ApiError]
+fn process(config: &ApiErrorConfig, ctx: RequestContext, res: Response) -> Response {
+    let Some(err) = res.extensions().get::<Box<ApiError>>() else {
+        return res;
+    };
+    let mut err = err.clone();
+    let mut modified = false;
+
+    // Report unexpected errors
+    if let Some(reporter) = &config.reporter
+        && let Some((subject, operation)) = &ctx.report
+        && let Some(source) = err.source()
+        && source.is_unexpected()
+    {
+        reporter.report(&ErrorReport {
+            error: source,
+            request_id: ctx.request_id,
+            subject: subject.get(),
+            operation: Some(operation),
+        });
+    }
+
+    // Include the problem type
+    if let Some(base) = &config.problem_type_base
+        && err.r#type().is_none()
+        && let Some(code) = err.error_code()
+    {
+        let r#type = format!("{base}{}", code.to_lowercase().replace('_', "-"));
+        err = err.with_type(r#type);
+        modified = true;
+    }
+
+    // Include the request id as the problem instance
+    if let Some(request_id) = ctx.request_id
+        && err.instance().is_none()
+    {
+        err = err.with_instance(format!("urn:request:{request_id}"));
+        modified = true;
+    }
+
+    // Localize the error details
+    if let Some(messages) = &config.messages
+        && let Some(code) = err.error_code()
+        && let Some(detail) = messages.localize(&ctx.accept_language, code, &err.message_fields())
+    {
+        err = err.with_detail(detail);
+        modified = true;
+    }
+
+    // Include the debug details
+    if (config.debug || ctx.debug || res.extensions().get::<ErrorDebug>().is_some())
+        && let Some(source) = err.source()
+    {
+        let details = debug_details(source);
+        err = err.with_error_info("debug", details);
+        modified = true;
+    }
+
+    // Render the error on the negotiated format
+    let mut res = match &config.template {
+        Some(template) if modified || ctx.format != ErrorFormat::Json => {
+            replace_body(res, err.render(ctx.format, template.as_ref()))
+        }
+        _ if modified => replace_body(res, err.into_response()),
+        _ => res,
+    };
+    if config.template.is_some() {
+        res.headers_mut().append(header::VARY, HeaderValue::from_static("accept"));
+    }
+    res
+}
+
+/// Replaces the body of the response with the given error response, keeping the response status and headers
+fn replace_body(res: Response, err: Response) -> Response {
+    let (mut parts, _) = res.into_parts();
+    let (new_parts, body) = err.into_parts();
+    parts.headers.remove(header::CONTENT_LENGTH);
+    if let Some(content_type) = new_parts.headers.get(header::CONTENT_TYPE) {
+        parts.headers.insert(header::CONTENT_TYPE, content_type.clone());
+    }
+    parts.extensions.extend(new_parts.extensions);
+    Response::from_parts(parts, body)
+}
+
+#[cfg(all(test, feature = "i18n"))]
+mod tests {
+    use axum::{body::Body, routing::get, Router};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::{
+        error::{err, GenericErrorCode, TomlErrorMessages, APPLICATION_PROBLEM_JSON},
+        request_id::RequestIdLayer,
+    };
+
+    #[tokio::test]
+    async fn test_api_error_layer() {
+        let messages = TomlErrorMessages::new()
+            .with_language("es", r#"NOT_FOUND = "No se ha encontrado el recurso""#)
+            .unwrap();
+        let router = Router::new()
+            .route(
+                "/",
+                get(|| async { Err::<(), Box<ApiError>>(err!(GenericErrorCode::NotFound).into()) }),
+            )
+            .layer(
+                ApiErrorLayer::new()
+                    .with_problem_type_base("https://example.com/problems")
+                    .with_messages(messages),
+            )
+            .layer(RequestIdLayer);
+
+        let req = Request::builder()
+            .uri("/")
+            .header(header::ACCEPT_LANGUAGE, "es-ES,es;q=0.9")
+            .body(Body::empty())
+            .unwrap();
+        let res = router.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), http::StatusCode::NOT_FOUND);
+        assert_eq!(res.headers()[header::CONTENT_TYPE], APPLICATION_PROBLEM_JSON);
+
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["type"], "https://example.com/problems/not-found");
+        assert_eq!(body["title"], "Not Found");
+        assert_eq!(body["status"], 404);
+        assert_eq!(body["detail"], "No se ha encontrado el recurso");
+        assert!(body["instance"].as_str().unwrap().starts_with("urn:request:"));
+        assert_eq!(body["info"]["errorCode"], "NOT_FOUND");
+        assert!(body.get("errors").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_debug_error() {
+        let router = Router::new()
+            .route(
+                "/",
+                get(|| async {
+                    let source = std::io::Error::other("connection refused");
+                    Err::<(), Box<ApiError>>(err!("Couldn't connect").with_source(source).into())
+                }),
+            )
+            .layer(ApiErrorLayer::new().with_debug(true));
+
+        let res = router.oneshot(Request::new(Body::empty())).await.unwrap();
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["detail"], "Internal server error");
+        assert_eq!(body["errors"]["debug"]["reason"], "Couldn't connect");
+        assert_eq!(body["errors"]["debug"]["causes"][0], "connection refused");
+        assert!(body["errors"]["debug"]["spanTrace"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_content_negotiation() {
+        let router = Router::new()
+            .route(
+                "/",
+                get(|| async { Err::<(), Box<ApiError>>(err!(GenericErrorCode::NotFound).into()) }),
+            )
+            .layer(ApiErrorLayer::new().with_html_template(HtmlErrorTemplate::new("<h1>{status} {title}</h1>")));
+
+        let res = router.clone().oneshot(Request::new(Body::empty())).await.unwrap();
+        assert_eq!(res.headers()[header::CONTENT_TYPE], APPLICATION_PROBLEM_JSON);
+        assert_eq!(res.headers()[header::VARY], "accept");
+
+        let req = Request::builder()
+            .uri("/")
+            .header(header::ACCEPT, "text/html,application/xhtml+xml,*/*;q=0.8")
+            .body(Body::empty())
+            .unwrap();
+        let res = router.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), http::StatusCode::NOT_FOUND);
+        assert_eq!(res.headers()[header::CONTENT_TYPE], "text/html; charset=utf-8");
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body, "<h1>404 Not Found</h1>");
+
+        let req = Request::builder()
+            .uri("/")
+            .header(header::ACCEPT, "text/plain")
+            .body(Body::empty())
+            .unwrap();
+        let res = router.oneshot(req).await.unwrap();
+        assert_eq!(res.headers()[header::CONTENT_TYPE], "text/plain; charset=utf-8");
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        assert!(body.starts_with(b"404 Not Found\n\n"));
+    }
+}
@@ -2,8 +2,21 @@
 
 crate::using! {
     pub core,
+    pub debug,
     pub api,
+    pub i18n,
+    pub layer,
+    pub report,
+    pub client,
+    pub render,
+    pub(crate) interpolate,
 }
 
 #[cfg(feature = "graphql")]
 crate::using!(pub graphql);
+
+#[cfg(feature = "garde")]
+crate::using!(pub validation);
+
+#[cfg(feature = "error-info-summary")]
+crate::using!(pub catalog);
@@ -0,0 +1,232 @@
+//! Content negotiation of [ApiError] responses.
+//!
+//! Errors are rendered as _Problem Details_ JSON by default, but browsers and CLI clients can get an HTML page or
+//! plain text instead, based on the `Accept` header. See
+//! [ApiErrorLayer::with_content_negotiation](super::ApiErrorLayer::with_content_negotiation).
+
+use std::{fmt::Write, path::Path, sync::Arc};
+
+use auto_impl::auto_impl;
+use axum::{
+    response::{IntoResponse, Response},
+    Extension,
+};
+use http::{header, HeaderMap, HeaderValue};
+
+use super::{interpolate_with, ApiError};
+
+/// Format used to render an [ApiError]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorFormat {
+    /// `application/problem+json`
+    #[default]
+    Json,
+    /// `text/html`
+    Html,
+    /// `text/plain`
+    Text,
+}
+
+impl ErrorFormat {
+    /// Formats in order of preference when the client accepts many of them with the same quality
+    const PREFERENCE: [ErrorFormat; 3] = [ErrorFormat::Json, ErrorFormat::Html, ErrorFormat::Text];
+
+    /// Selects the preferred format from the `Accept` header, defaulting to [Json](ErrorFormat::Json) when missing
+    /// or when none of the formats is accepted
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let accept = headers
+            .get_all(header::ACCEPT)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .filter_map(parse_media_range)
+            .collect::<Vec<_>>();
+        if accept.is_empty() {
+            return Self::default();
+        }
+
+        let mut ret = Self::default();
+        let mut best = 0.0;
+        for format in Self::PREFERENCE {
+            // The quality is given by the most specific range matching the format
+            let quality = accept
+                .iter()
+                .filter_map(|(range, quality)| format.specificity(range).map(|s| (s, *quality)))
+                .max_by_key(|(specificity, _)| *specificity)
+                .map(|(_, quality)| quality)
+                .unwrap_or(0.0);
+            if quality > best {
+                best = quality;
+                ret = format;
+            }
+        }
+        ret
+    }
+
+    /// Retrieves how specific the given media range is for this format, if it matches
+    fn specificity(&self, range: &str) -> Option<u8> {
+        let (media_types, main_type): (&[&str], &str) = match self {
+            ErrorFormat::Json => (&[super::APPLICATION_PROBLEM_JSON, "application/json"], "application/*"),
+            ErrorFormat::Html => (&["text/html"], "text/*"),
+            ErrorFormat::Text => (&["text/plain"], "text/*"),
+        };
+        if media_types.iter().any(|t| t.eq_ignore_ascii_case(range)) {
+            Some(2)
+        } else if range == main_type {
+            Some(1)
+        } else if range == "*/*" {
+            Some(0)
+        } else {
+            None
+        }
+    }
+}
+
+/// Parses a media range from the `Accept` header, returning it along with its quality
+fn parse_media_range(value: &str) -> Option<(String, f32)> {
+    let mut params = value.split(';');
+    let range = params.next()?.trim().to_ascii_lowercase();
+    if range.is_empty() {
+        return None;
+    }
+    let quality = params
+        .filter_map(|p| p.split_once('='))
+        .find(|(k, _)| k.trim().eq_ignore_ascii_case("q"))
+        .and_then(|(_, v)| v.trim().parse::<f32>().ok())
+        .unwrap_or(1.0);
+    Some((range, quality))
+}
+
+/// Template used to render [ApiError] HTML pages
+#[auto_impl(Box, Arc)]
+pub trait ErrorTemplate: Send + Sync + 'static {
+    /// Renders the HTML page for the given error
+    fn render(&self, err: &ApiError) -> String;
+}
+
+/// [ErrorTemplate] replacing the `{status}`, `{title}`, `{detail}`, `{type}` and `{instance}` placeholders with the
+/// HTML-escaped values of the error
+#[derive(Debug, Clone)]
+pub struct HtmlErrorTemplate {
+    template: Arc<str>,
+}
+
+impl Default for HtmlErrorTemplate {
+    fn default() -> Self {
+        Self::new(include_str!("error.html"))
+    }
+}
+
+impl HtmlErrorTemplate {
+    /// Creates a new template from its content
+    pub fn new(template: impl Into<Arc<str>>) -> Self {
+        Self {
+            template: template.into(),
+        }
+    }
+
+    /// Reads the template from the given file
+    pub fn from_file(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Ok(Self::new(std::fs::read_to_string(path)?))
+    }
+}
+
+impl ErrorTemplate for HtmlErrorTemplate {
+    fn render(&self, err: &ApiError) -> String {
+        let status = err.status();
+        let value = |name: &str| match name {
+            "status" => Some(status.as_str()),
+            "title" => Some(err.title()),
+            "detail" => Some(err.detail()),
+            "type" => Some(err.r#type().unwrap_or("about:blank")),
+            "instance" => Some(err.instance().unwrap_or_default()),
+            _ => None,
+        };
+        interpolate_with(&self.template, value, escape_html)
+    }
+}
+
+/// Pushes the given text into the buffer, escaping the HTML special characters
+fn escape_html(buf: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => buf.push_str("&amp;"),
+            '<' => buf.push_str("&lt;"),
+            '>' => buf.push_str("&gt;"),
+            '"' => buf.push_str("&quot;"),
+            '\'' => buf.push_str("&#39;"),
+            c => buf.push(c),
+        }
+    }
+}
+
+impl ApiError {
+    /// Renders the error on the given format, using the template for HTML pages.
+    ///
+    /// Every format has the same status, headers and extensions than the [IntoResponse] implementation.
+    pub fn render(self: Box<Self>, format: ErrorFormat, template: &dyn ErrorTemplate) -> Response {
+        let (content_type, body) = match format {
+            ErrorFormat::Json => return self.into_response(),
+            ErrorFormat::Html => ("text/html; charset=utf-8", template.render(&self)),
+            ErrorFormat::Text => {
+                let mut body = format!("{} {}\n\n{}\n", self.status().as_str(), self.title(), self.detail());
+                if let Some(instance) = self.instance() {
+                    let _ = writeln!(body, "\nInstance: {instance}");
+                }
+                ("text/plain; charset=utf-8", body)
+            }
+        };
+        let mut res = (self.status(), Extension(self.clone()), body).into_response();
+        if let Some(headers) = self.headers() {
+            res.headers_mut().extend(headers.clone());
+        }
+        res.headers_mut()
+            .insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accept(value: &str) -> ErrorFormat {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, HeaderValue::from_str(value).unwrap());
+        ErrorFormat::from_headers(&headers)
+    }
+
+    #[test]
+    fn test_error_format() {
+        assert_eq!(ErrorFormat::from_headers(&HeaderMap::new()), ErrorFormat::Json);
+        assert_eq!(accept("*/*"), ErrorFormat::Json);
+        assert_eq!(accept("image/png"), ErrorFormat::Json);
+        assert_eq!(accept("application/json"), ErrorFormat::Json);
+        assert_eq!(accept("text/plain"), ErrorFormat::Text);
+        assert_eq!(accept("text/*"), ErrorFormat::Html);
+        assert_eq!(accept("text/*, text/html;q=0.5"), ErrorFormat::Text);
+        assert_eq!(
+            accept("text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8"),
+            ErrorFormat::Html
+        );
+        assert_eq!(accept("text/plain;q=0.5, application/problem+json"), ErrorFormat::Json);
+    }
+
+    #[test]
+    fn test_html_template() {
+        let err = ApiError::new(http::StatusCode::NOT_FOUND, "Couldn't find <script>");
+        let template = HtmlErrorTemplate::new("<h1>{status} {title}</h1><p>{detail}</p><p>{instance}</p>");
+        assert_eq!(
+            template.render(&err),
+            "<h1>404 Not Found</h1><p>Couldn&#39;t find &lt;script&gt;</p><p></p>"
+        );
+
+        // Values containing placeholders and unknown placeholders are kept as they are
+        let err = ApiError::new(http::StatusCode::NOT_FOUND, "Couldn't find {title}");
+        let template = HtmlErrorTemplate::new("<style>p {color: red}</style><p>{detail}</p><p>{unknown}</p>");
+        assert_eq!(
+            template.render(&err),
+            "<style>p {color: red}</style><p>Couldn&#39;t find {title}</p><p>{unknown}</p>"
+        );
+    }
+}
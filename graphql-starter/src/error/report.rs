@@ -0,0 +1,464 @@
+//! Reporting of unexpected errors.
+//!
+//! Every unexpected [Error] responded to clients can be sent to an [ErrorReporter], along with the information of
+//! the request that caused it. Errors are grouped by their code and the location where they were created, so the
+//! same failure happening many times can be easily spotted.
+
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex, OnceLock,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use auto_impl::auto_impl;
+use serde::Serialize;
+use tracing_error::SpanTrace;
+
+use super::Error;
+use crate::request_id::RequestId;
+
+/// Sink for unexpected errors
+#[auto_impl(Box, Arc)]
+pub trait ErrorReporter: Send + Sync + 'static {
+    /// Reports an unexpected error
+    fn report(&self, report: &ErrorReport<'_>);
+}
+
+/// Unexpected error to be reported, along with the request information
+pub struct ErrorReport<'a> {
+    /// The error
+    pub error: &'a Error,
+    /// The id of the request (if any)
+    pub request_id: Option<RequestId>,
+    /// The authenticated subject (if any)
+    pub subject: Option<&'a str>,
+    /// The route or GraphQL operation name (if any)
+    pub operation: Option<&'a str>,
+}
+
+impl ErrorReport<'_> {
+    /// Retrieves the span trace of the error
+    pub fn span_trace(&self) -> &SpanTrace {
+        &self.error.context
+    }
+
+    /// Retrieves the key used to group the error, based on the code and the location where it was created
+    pub fn group(&self) -> String {
+        let location = self.error.location();
+        format!(
+            "{}@{}:{}:{}",
+            self.error.info.code(),
+            location.file(),
+            location.line(),
+            location.column()
+        )
+    }
+
+    /// Builds an owned and serializable version of this report
+    pub fn to_reported(&self) -> ReportedError {
+        let status = self.error.info.status();
+        ReportedError {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            group: self.group(),
+            status_code: status.as_u16(),
+            error_code: self.error.info.code(),
+            reason: self.error.reason_or_message(),
+            causes: self.error.causes().map(|c| c.to_string()).collect(),
+            location: self.error.location().to_string(),
+            request_id: self.request_id.map(|id| id.to_string()),
+            subject: self.subject.map(ToOwned::to_owned),
+            operation: self.operation.map(ToOwned::to_owned),
+            span_trace: self.span_trace().to_string(),
+        }
+    }
+}
+
+/// Owned version of an [ErrorReport]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportedError {
+    /// Milliseconds since the Unix epoch when the error was reported
+    pub timestamp: u64,
+    /// The group key of the error
+    pub group: String,
+    /// The HTTP status code
+    pub status_code: u16,
+    /// The error code
+    pub error_code: &'static str,
+    /// The reason of the error, or the default error message if there's none
+    pub reason: String,
+    /// The chain of causes of the error
+    pub causes: Vec<String>,
+    /// The source code location where the error was created
+    pub location: String,
+    /// The id of the request (if any)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// The authenticated subject (if any)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    /// The route or GraphQL operation name (if any)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operation: Option<String>,
+    /// The span trace of the error
+    pub span_trace: String,
+}
+
+/// Slot for the authenticated subject of a request, to be included on the [ErrorReport].
+///
+/// It's added to the request extensions by [ApiErrorLayer](super::ApiErrorLayer) when reporting is enabled and
+/// filled by the `Auth` extractor of the `auth` feature. On GraphQL, it's included on the context data by the handlers.
+#[derive(Debug, Clone, Default)]
+pub struct ReportedSubject(Arc<OnceLock<String>>);
+
+impl ReportedSubject {
+    /// Creates a new slot with the given subject
+    pub fn new(subject: Option<String>) -> Self {
+        let ret = Self::default();
+        if let Some(subject) = subject {
+            ret.set(subject);
+        }
+        ret
+    }
+
+    /// Sets the subject, if not already set
+    pub fn set(&self, subject: impl Into<String>) {
+        let _ = self.0.set(subject.into());
+    }
+
+    /// Retrieves the subject (if set)
+    pub fn get(&self) -> Option<&str> {
+        self.0.get().map(String::as_str)
+    }
+}
+
+/// Group of errors reported to the [InMemoryErrorReporter]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorGroup {
+    /// The group key
+    pub group: String,
+    /// Number of errors reported on this group
+    pub count: u64,
+    /// Milliseconds since the Unix epoch when the first error was reported
+    pub first_seen: u64,
+    /// The last error reported
+    pub last: ReportedError,
+}
+
+/// [ErrorReporter] that keeps the groups of errors in memory, to be exposed on a dashboard.
+///
+/// It can be cheaply cloned, sharing the same groups.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryErrorReporter {
+    groups: Arc<Mutex<HashMap<String, ErrorGroup>>>,
+}
+
+impl InMemoryErrorReporter {
+    /// Creates a new empty reporter
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Retrieves the groups of errors, the most recent first
+    pub fn groups(&self) -> Vec<ErrorGroup> {
+        let mut groups = self.lock().values().cloned().collect::<Vec<_>>();
+        groups.sort_by_key(|g| std::cmp::Reverse(g.last.timestamp));
+        groups
+    }
+
+    /// Removes every group
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, ErrorGroup>> {
+        self.groups.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl ErrorReporter for InMemoryErrorReporter {
+    fn report(&self, report: &ErrorReport<'_>) {
+        let reported = report.to_reported();
+        self.lock()
+            .entry(reported.group.clone())
+            .and_modify(|g| {
+                g.count += 1;
+                g.last = reported.clone();
+            })
+            .or_insert_with(|| ErrorGroup {
+                group: reported.group.clone(),
+                count: 1,
+                first_seen: reported.timestamp,
+                last: reported,
+            });
+    }
+}
+
+/// [ErrorReporter] that appends the errors to a file, one JSON object per line.
+///
+/// Errors of the same group are written at most once every dedup interval (one minute by default), the line including
+/// the number of `occurrences` since the last one written. The suppressed errors are written once the interval
+/// elapses, even if the group doesn't recur, or when the reporter is dropped.
+///
+/// Lines are written by a background thread, so reporting never blocks on the file.
+pub struct JsonLinesErrorReporter {
+    dedup_interval: Duration,
+    file: Mutex<Option<File>>,
+    worker: OnceLock<Option<(Sender<ReportedError>, JoinHandle<()>)>>,
+}
+
+impl JsonLinesErrorReporter {
+    /// Opens the given file to append the errors, creating it if missing
+    pub fn new(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            dedup_interval: Duration::from_secs(60),
+            file: Mutex::new(Some(file)),
+            worker: OnceLock::new(),
+        })
+    }
+
+    /// Updates the interval to deduplicate errors of the same group, zero disables deduplication
+    pub fn with_dedup_interval(mut self, dedup_interval: Duration) -> Self {
+        self.dedup_interval = dedup_interval;
+        self
+    }
+
+    /// Spawns the background thread writing the lines
+    fn spawn_worker(&self) -> Option<(Sender<ReportedError>, JoinHandle<()>)> {
+        let file = self.file.lock().unwrap_or_else(|e| e.into_inner()).take()?;
+        let writer = JsonLinesWriter {
+            file,
+            dedup_interval: self.dedup_interval,
+            groups: HashMap::new(),
+            last_flush: Instant::now(),
+        };
+        let (tx, rx) = mpsc::channel();
+        match thread::Builder::new()
+            .name("error-reporter".into())
+            .spawn(move || writer.run(rx))
+        {
+            Ok(handle) => Some((tx, handle)),
+            Err(err) => {
+                tracing::error!("Couldn't spawn the error reporter thread: {err}");
+                None
+            }
+        }
+    }
+}
+
+impl ErrorReporter for JsonLinesErrorReporter {
+    fn report(&self, report: &ErrorReport<'_>) {
+        if let Some((tx, _)) = self.worker.get_or_init(|| self.spawn_worker())
+            && tx.send(report.to_reported()).is_err()
+        {
+            tracing::error!("Couldn't send the error report, the writer has stopped");
+        }
+    }
+}
+
+impl Drop for JsonLinesErrorReporter {
+    fn drop(&mut self) {
+        // Stop the writer, waiting for it to write the suppressed errors
+        if let Some(Some((tx, handle))) = self.worker.take() {
+            drop(tx);
+            if handle.join().is_err() {
+                tracing::error!("The error reporter thread panicked");
+            }
+        }
+    }
+}
+
+/// Background writer of the [JsonLinesErrorReporter]
+struct JsonLinesWriter {
+    file: File,
+    dedup_interval: Duration,
+    groups: HashMap<String, JsonLinesGroup>,
+    last_flush: Instant,
+}
+
+/// Deduplication state of a group of errors
+struct JsonLinesGroup {
+    last_written: Instant,
+    suppressed: u64,
+    last_suppressed: Option<ReportedError>,
+}
+
+#[derive(Serialize)]
+struct JsonLine<'a> {
+    #[serde(flatten)]
+    error: &'a ReportedError,
+    occurrences: u64,
+}
+
+impl JsonLinesWriter {
+    /// Writes the received errors until every sender is dropped
+    fn run(mut self, rx: Receiver<ReportedError>) {
+        loop {
+            let received = if self.dedup_interval.is_zero() {
+                rx.recv().map_err(|_| RecvTimeoutError::Disconnected)
+            } else {
+                rx.recv_timeout(self.dedup_interval)
+            };
+            match received {
+                Ok(reported) => self.report(reported),
+                Err(RecvTimeoutError::Timeout) => (),
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.flush(true);
+                    return;
+                }
+            }
+            if self.last_flush.elapsed() >= self.dedup_interval {
+                self.flush(false);
+            }
+        }
+    }
+
+    /// Writes the error, unless the group was recently written
+    fn report(&mut self, reported: ReportedError) {
+        let now = Instant::now();
+        let occurrences = match self.groups.get_mut(&reported.group) {
+            Some(group) if now.duration_since(group.last_written) < self.dedup_interval => {
+                group.suppressed += 1;
+                group.last_suppressed = Some(reported);
+                return;
+            }
+            Some(group) => {
+                group.last_written = now;
+                group.last_suppressed = None;
+                std::mem::take(&mut group.suppressed) + 1
+            }
+            None => {
+                self.groups.insert(
+                    reported.group.clone(),
+                    JsonLinesGroup {
+                        last_written: now,
+                        suppressed: 0,
+                        last_suppressed: None,
+                    },
+                );
+                1
+            }
+        };
+        self.write(&reported, occurrences);
+    }
+
+    /// Writes the suppressed errors of the groups whose interval has elapsed (or every one of them), forgetting the
+    /// idle groups
+    fn flush(&mut self, all: bool) {
+        let now = Instant::now();
+        let dedup_interval = self.dedup_interval;
+        let mut pending = Vec::new();
+        self.groups.retain(|_, group| {
+            let elapsed = now.duration_since(group.last_written) >= dedup_interval;
+            if (elapsed || all)
+                && let Some(last) = group.last_suppressed.take()
+            {
+                pending.push((last, std::mem::take(&mut group.suppressed)));
+                group.last_written = now;
+                return true;
+            }
+            !elapsed
+        });
+        for (reported, occurrences) in pending {
+            self.write(&reported, occurrences);
+        }
+        self.last_flush = now;
+    }
+
+    fn write(&mut self, error: &ReportedError, occurrences: u64) {
+        let line = JsonLine { error, occurrences };
+        let res = serde_json::to_writer(&mut self.file, &line)
+            .map_err(io::Error::from)
+            .and_then(|_| self.file.write_all(b"\n"));
+        if let Err(err) = res {
+            tracing::error!("Couldn't write the error report: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{err, GenericErrorCode};
+
+    #[test]
+    fn test_in_memory_reporter() {
+        let reporter = InMemoryErrorReporter::new();
+        let errors = (0..3).map(|_| err!("Repeated")).collect::<Vec<_>>();
+        let other = err!(GenericErrorCode::GatewayTimeout);
+        for error in errors.iter().chain([&other]) {
+            reporter.report(&ErrorReport {
+                error,
+                request_id: None,
+                subject: Some("user:1"),
+                operation: Some("GET /"),
+            });
+        }
+
+        let groups = reporter.groups();
+        assert_eq!(groups.len(), 2);
+        let repeated = groups.iter().find(|g| g.last.reason == "Repeated").unwrap();
+        assert_eq!(repeated.count, 3);
+        assert!(repeated.group.starts_with("INTERNAL_SERVER_ERROR@"));
+        assert!(repeated.last.location.contains("report.rs"));
+        assert_eq!(repeated.last.subject.as_deref(), Some("user:1"));
+    }
+
+    #[test]
+    fn test_json_lines_reporter() {
+        let path = std::env::temp_dir().join(format!("errors-{}.jsonl", std::process::id()));
+        let reporter = JsonLinesErrorReporter::new(&path).unwrap();
+        let errors = (0..3).map(|_| err!("Repeated")).collect::<Vec<_>>();
+        for error in &errors {
+            reporter.report(&ErrorReport {
+                error,
+                request_id: None,
+                subject: None,
+                operation: None,
+            });
+        }
+
+        // The suppressed errors are written when dropped
+        drop(reporter);
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines = content
+            .lines()
+            .map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["errorCode"], "INTERNAL_SERVER_ERROR");
+        assert_eq!(lines[0]["occurrences"], 1);
+        assert_eq!(lines[1]["occurrences"], 2);
+
+        // Or once the interval elapses, even if the group doesn't recur
+        let reporter = JsonLinesErrorReporter::new(&path)
+            .unwrap()
+            .with_dedup_interval(Duration::from_millis(20));
+        for error in &errors {
+            reporter.report(&ErrorReport {
+                error,
+                request_id: None,
+                subject: None,
+                operation: None,
+            });
+        }
+        std::thread::sleep(Duration::from_millis(200));
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), 2);
+        drop(reporter);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
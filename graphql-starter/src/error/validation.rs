@@ -0,0 +1,105 @@
+use error_info::ErrorInfo;
+use garde::{Path, Report};
+use http::StatusCode;
+
+use super::Error;
+
+/// Name of the [Error] property containing the validation errors of each field.
+///
+/// It's included as an extension on GraphQL errors, and every field is included on the
+/// [ApiError](super::ApiError) `errors`.
+pub const VALIDATION_ERRORS: &str = "validationErrors";
+
+/// Validation related errors
+#[derive(Debug, Clone, Copy, ErrorInfo)]
+pub enum ValidationErrorCode {
+    #[error(status = StatusCode::UNPROCESSABLE_ENTITY, message = "The request contains invalid values")]
+    ValidationFailed,
+}
+
+/// Converts a [garde] [Report] into a [`Box<Error>`](Error), including the messages of every invalid field keyed by
+/// its JSON path on the [VALIDATION_ERRORS] property.
+impl From<Report> for Box<Error> {
+    #[track_caller]
+    fn from(report: Report) -> Self {
+        let errors = validation_errors(&report);
+        Error::new(ValidationErrorCode::ValidationFailed)
+            .with_property(VALIDATION_ERRORS, errors)
+            .with_error_source(report)
+    }
+}
+
+/// Builds a JSON object with the messages of every invalid field, keyed by its JSON path
+pub fn validation_errors(report: &Report) -> serde_json::Value {
+    let mut errors = serde_json::Map::new();
+    for (path, error) in report.iter() {
+        let messages = errors
+            .entry(json_path(path))
+            .or_insert_with(|| serde_json::Value::Array(Vec::new()));
+        if let serde_json::Value::Array(messages) = messages {
+            messages.push(error.message().into());
+        }
+    }
+    serde_json::Value::Object(errors)
+}
+
+/// Formats a [garde] [Path] as a JSON path, like `$.items[0].name`
+fn json_path(path: &Path) -> String {
+    let path = path.to_string();
+    if path.is_empty() || path.starts_with('[') {
+        format!("${path}")
+    } else {
+        format!("$.{path}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use garde::Validate;
+
+    use super::*;
+    use crate::error::ApiError;
+
+    struct Item {
+        name: String,
+    }
+
+    struct Input {
+        items: Vec<Item>,
+    }
+
+    impl Validate for Input {
+        type Context = ();
+
+        fn validate_into(&self, _ctx: &(), parent: &mut dyn FnMut() -> Path, report: &mut Report) {
+            if self.items.is_empty() {
+                report.append(parent().join("items"), garde::Error::new("must not be empty"));
+            }
+            for (idx, item) in self.items.iter().enumerate() {
+                if item.name.len() < 3 {
+                    let path = parent().join("items").join(idx).join("name");
+                    report.append(path.clone(), garde::Error::new("length is lower than 3"));
+                    report.append(path, garde::Error::new("must be uppercase"));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let input = Input {
+            items: vec![Item { name: "ABCD".into() }, Item { name: "a".into() }],
+        };
+        let err: Box<Error> = input.validate().unwrap_err().into();
+        assert_eq!(err.info().code(), "VALIDATION_FAILED");
+        assert!(!err.is_unexpected());
+
+        let api_err = ApiError::from_err(err);
+        assert_eq!(api_err.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            api_err.errors()["$.items[1].name"],
+            serde_json::json!(["length is lower than 3", "must be uppercase"])
+        );
+        assert!(!api_err.errors().contains_key(VALIDATION_ERRORS));
+    }
+}
@@ -0,0 +1,20 @@
+use async_graphql::Object;
+
+use crate::error::{ErrorCatalog, ErrorCatalogEntry};
+
+/// Query exposing the [ErrorCatalog], to be mounted on the schema root query.
+///
+/// ``` rust ignore
+/// #[derive(MergedObject, Default)]
+/// pub struct Query(MyQuery, ErrorCatalogQuery);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ErrorCatalogQuery;
+
+#[Object]
+impl ErrorCatalogQuery {
+    /// Every error that can be returned by the API, sorted by status and code
+    async fn error_catalog(&self) -> &'static [ErrorCatalogEntry] {
+        ErrorCatalog::global().entries()
+    }
+}
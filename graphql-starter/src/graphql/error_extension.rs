@@ -0,0 +1,176 @@
+use std::{collections::HashMap, sync::Arc};
+
+use async_graphql::{
+    extensions::{Extension, ExtensionContext, ExtensionFactory, NextExecute, NextRequest, NextSubscribe},
+    futures_util::stream::BoxStream,
+    Response, ServerError, Value,
+};
+use futures_util::StreamExt;
+
+use crate::{
+    axum::extract::AcceptLanguage,
+    error::{debug_details, Error, ErrorDebug, ErrorMessages, ErrorReport, ErrorReporter, ReportedSubject},
+    request_id::RequestId,
+};
+
+/// GraphQL [Extension] to post-process the errors on the responses, analogous to
+/// [ApiErrorLayer](crate::error::ApiErrorLayer) for REST responses.
+///
+/// It relies on the context data included by the GraphQL handlers, like the [AcceptLanguage], [ErrorDebug],
+/// [RequestId] or [ReportedSubject].
+///
+/// ``` rust ignore
+/// let schema = Schema::build(Query, Mutation, Subscription)
+///     .extension(GraphQLErrorExtension::new().with_messages(TomlErrorMessages::from_dir("./i18n")?))
+///     .finish();
+/// ```
+#[derive(Clone, Default)]
+pub struct GraphQLErrorExtension {
+    debug: bool,
+    messages: Option<Arc<dyn ErrorMessages>>,
+    reporter: Option<Arc<dyn ErrorReporter>>,
+}
+
+impl GraphQLErrorExtension {
+    /// Creates a new [GraphQLErrorExtension], which doesn't modify errors until configured
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Localizes the error messages to the preferred language from the `Accept-Language` header
+    pub fn with_messages(mut self, messages: impl ErrorMessages) -> Self {
+        self.messages = Some(Arc::new(messages));
+        self
+    }
+
+    /// Reports the unexpected errors to the given [ErrorReporter], along with the request information
+    pub fn with_reporter(mut self, reporter: impl ErrorReporter) -> Self {
+        self.reporter = Some(Arc::new(reporter));
+        self
+    }
+
+    /// Enables the [debug mode](crate::error::ErrorDebug) for every request, it must only be enabled on development
+    /// environments.
+    ///
+    /// When disabled, it can still be enabled per request with the [ErrorDebug] context data.
+    pub fn with_debug(mut self, debug: bool) -> Self {
+        self.debug = debug;
+        self
+    }
+
+    /// Reports the unexpected errors
+    fn report(&self, ctx: &RequestContext, operation: Option<&str>, errors: &[ServerError]) {
+        let Some(reporter) = &self.reporter else {
+            return;
+        };
+        for err in errors {
+            if let Some(source) = err.source::<Error>()
+                && source.is_unexpected()
+            {
+                reporter.report(&ErrorReport {
+                    error: source,
+                    request_id: ctx.request_id,
+                    subject: ctx.subject.as_ref().and_then(|s| s.get()),
+                    operation,
+                });
+            }
+        }
+    }
+
+    /// Post-processes the given errors
+    fn process(&self, ctx: &RequestContext, errors: &mut [ServerError]) {
+        for err in errors {
+            // Include the debug details, which also unmasks unexpected errors
+            if (self.debug || ctx.debug)
+                && let Some(source) = err.source::<Error>()
+                && let Ok(details) = Value::from_json(debug_details(source))
+            {
+                err.extensions.get_or_insert_with(Default::default).set("debug", details);
+            }
+
+            // Localize the error message
+            if let Some(messages) = &self.messages
+                && let Some(accept_language) = &ctx.accept_language
+                && let Some(extensions) = &err.extensions
+                && let Some(Value::String(code)) = extensions.get("errorCode")
+            {
+                let fields = match extensions.get("messageFields") {
+                    Some(Value::Object(fields)) => fields
+                        .iter()
+                        .filter_map(|(k, v)| match v {
+                            Value::String(v) => Some((k.to_string(), v.clone())),
+                            _ => None,
+                        })
+                        .collect(),
+                    _ => HashMap::new(),
+                };
+                if let Some(message) = messages.localize(accept_language, code, &fields) {
+                    err.message = message;
+                }
+            }
+        }
+    }
+}
+
+impl ExtensionFactory for GraphQLErrorExtension {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(self.clone())
+    }
+}
+
+#[async_graphql::async_trait::async_trait]
+impl Extension for GraphQLErrorExtension {
+    async fn request(&self, ctx: &ExtensionContext<'_>, next: NextRequest<'_>) -> Response {
+        let req_ctx = RequestContext::new(ctx);
+        let mut res = next.run(ctx).await;
+        self.process(&req_ctx, &mut res.errors);
+        res
+    }
+
+    async fn execute(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        operation_name: Option<&str>,
+        next: NextExecute<'_>,
+    ) -> Response {
+        let res = next.run(ctx, operation_name).await;
+        self.report(&RequestContext::new(ctx), operation_name, &res.errors);
+        res
+    }
+
+    fn subscribe<'s>(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        stream: BoxStream<'s, Response>,
+        next: NextSubscribe<'_>,
+    ) -> BoxStream<'s, Response> {
+        let this = self.clone();
+        let req_ctx = RequestContext::new(ctx);
+        next.run(ctx, stream)
+            .map(move |mut res| {
+                this.report(&req_ctx, None, &res.errors);
+                this.process(&req_ctx, &mut res.errors);
+                res
+            })
+            .boxed()
+    }
+}
+
+/// Request information needed to post-process the errors
+struct RequestContext {
+    debug: bool,
+    accept_language: Option<AcceptLanguage>,
+    request_id: Option<RequestId>,
+    subject: Option<ReportedSubject>,
+}
+
+impl RequestContext {
+    fn new(ctx: &ExtensionContext<'_>) -> Self {
+        Self {
+            debug: ctx.data_opt::<ErrorDebug>().is_some(),
+            accept_language: ctx.data_opt::<AcceptLanguage>().cloned(),
+            request_id: ctx.data_opt::<RequestId>().copied(),
+            subject: ctx.data_opt::<ReportedSubject>().cloned(),
+        }
+    }
+}
@@ -1,6 +1,6 @@
 //! Manually implementing GraphQLBatchRequest to customize multipart options, as [recommended](https://github.com/async-graphql/async-graphql/issues/1220).
 
-use std::{io::ErrorKind, marker::PhantomData};
+use std::marker::PhantomData;
 
 use async_graphql::{futures_util::TryStreamExt, http::MultipartOptions, ParseRequestError};
 use axum::{
@@ -99,10 +99,10 @@ where
         if req.method() == Method::GET {
             let uri = req.uri();
             let res = async_graphql::http::parse_query_string(uri.query().unwrap_or_default()).map_err(|err| {
-                ParseRequestError::Io(std::io::Error::new(
-                    ErrorKind::Other,
-                    format!("failed to parse graphql request from uri query: {}", err),
-                ))
+                ParseRequestError::Io(std::io::Error::other(format!(
+                    "failed to parse graphql request from uri query: {}",
+                    err
+                )))
             });
             Ok(Self(async_graphql::BatchRequest::Single(res?), PhantomData))
         } else {
@@ -114,7 +114,7 @@ where
             let body_stream = req
                 .into_body()
                 .into_data_stream()
-                .map_err(|err| std::io::Error::new(ErrorKind::Other, err.to_string()));
+                .map_err(|err| std::io::Error::other(err.to_string()));
             let body_reader = tokio_util::io::StreamReader::new(body_stream).compat();
             Ok(Self(
                 async_graphql::http::receive_batch_body(content_type, body_reader, FromRef::from_ref(state)).await?,
@@ -1,27 +1,104 @@
-use std::marker::PhantomData;
+use std::{borrow::Cow, fmt::Display, marker::PhantomData};
 
-use async_graphql::{Context, Guard, Result};
+use async_graphql::{Context, Guard, Result, Value};
+use http::header::WWW_AUTHENTICATE;
 
 use crate::{
-    auth::{AuthErrorCode, AuthState, AuthorizationService, Subject},
+    auth::{
+        client_value, fill_template, AuthErrorCode, AuthState, AuthorizationService, AuthzCache, RequiredScopes,
+        Subject, SubjectScopes,
+    },
     error::{err, GraphQLError},
 };
 
+type ObjectFn = Box<dyn Fn(&Context<'_>) -> Result<String> + Send + Sync>;
+
 /// Authorization [Guard].
 ///
 /// This guard will use the `Option<S>` and the state from the GraphQL context
 /// to authorize an action, failing if they're not available.
+///
+/// If there's an [AuthzCache] on the context, decisions are cached for the whole request.
+///
+/// The object can be static or built at runtime, from the field arguments or the parent object being resolved:
+///
+/// ``` rust ignore
+/// type Guard = AuthGuard<Subject, State>;
+///
+/// #[Object]
+/// impl Query {
+///     // Static object
+///     #[graphql(guard = "Guard::new(\"read\", \"documents\")")]
+///     async fn documents(&self) -> Vec<Document> { .. }
+///
+///     // Template filled from the field arguments
+///     #[graphql(guard = "Guard::with_template(\"read\", \"document:{id}\")")]
+///     async fn document(&self, id: ID) -> Document { .. }
+///
+///     // Template filled from a resolved argument, as it might be omitted on the query
+///     #[graphql(guard = "Guard::with_template(\"read\", \"folder:{folder}\").with_value(\"folder\", &folder)")]
+///     async fn folder(&self, #[graphql(default = "root")] folder: String) -> Vec<Document> { .. }
+/// }
+///
+/// #[Object]
+/// impl Document {
+///     // Template filled from the parent object
+///     #[graphql(guard = "Guard::with_template(\"read\", \"user:{owner}\").with_value(\"owner\", &self.owner)")]
+///     async fn owner(&self) -> User { .. }
+///
+///     // Object computed in the guard expression, where both arguments and `self` are available
+///     #[graphql(guard = "Guard::with_object(\"edit\", format!(\"document:{}\", self.id))")]
+///     async fn draft(&self) -> String { .. }
+/// }
+/// ```
 pub struct AuthGuard<S: Subject, St: AuthState<S>> {
     relation: &'static str,
-    object: &'static str,
+    object: GuardObject,
     // Needed for the compiler
     sub_type: PhantomData<S>,
     state_type: PhantomData<St>,
 }
 
+/// Object to authorize on an [AuthGuard]
+enum GuardObject {
+    Static(Cow<'static, str>),
+    Fn(ObjectFn),
+}
+
 impl<S: Subject, St: AuthState<S>> AuthGuard<S, St> {
     /// Creates a new authorization guard for a given relation of an object.
     pub fn new(relation: &'static str, object: &'static str) -> Self {
+        Self::with(relation, GuardObject::Static(Cow::Borrowed(object)))
+    }
+
+    /// Creates a new authorization guard for a given relation of an object built at runtime.
+    pub fn with_object(relation: &'static str, object: impl Into<String>) -> Self {
+        Self::with(relation, GuardObject::Static(Cow::Owned(object.into())))
+    }
+
+    /// Creates a new authorization guard for a given relation of an object template.
+    ///
+    /// The `{name}` placeholders are filled with the [values](AuthTemplateGuard::with_value) or else with the scalar
+    /// field arguments present on the query.
+    pub fn with_template(relation: &'static str, template: &'static str) -> AuthTemplateGuard<S, St> {
+        AuthTemplateGuard {
+            relation,
+            template,
+            values: Vec::new(),
+            sub_type: PhantomData,
+            state_type: PhantomData,
+        }
+    }
+
+    /// Creates a new authorization guard for a given relation of an object returned by the closure.
+    pub fn with_fn<F>(relation: &'static str, object: F) -> Self
+    where
+        F: Fn(&Context<'_>) -> Result<String> + Send + Sync + 'static,
+    {
+        Self::with(relation, GuardObject::Fn(Box::new(object)))
+    }
+
+    fn with(relation: &'static str, object: GuardObject) -> Self {
         AuthGuard {
             relation,
             object,
@@ -32,16 +109,217 @@ impl<S: Subject, St: AuthState<S>> AuthGuard<S, St> {
 }
 
 impl<S: Subject, St: AuthState<S>> Guard for AuthGuard<S, St> {
+    async fn check(&self, ctx: &Context<'_>) -> Result<()> {
+        authorize::<S, St>(ctx, self.relation, || {
+            Ok(match &self.object {
+                GuardObject::Static(object) => Cow::Borrowed(object),
+                GuardObject::Fn(f) => Cow::Owned(f(ctx)?),
+            })
+        })
+        .await
+    }
+}
+
+/// Authorization [Guard] for an object template, see [AuthGuard::with_template]
+pub struct AuthTemplateGuard<S: Subject, St: AuthState<S>> {
+    relation: &'static str,
+    template: &'static str,
+    values: Vec<(&'static str, String)>,
+    // Needed for the compiler
+    sub_type: PhantomData<S>,
+    state_type: PhantomData<St>,
+}
+
+impl<S: Subject, St: AuthState<S>> AuthTemplateGuard<S, St> {
+    /// Includes a value to fill the `{name}` placeholders of the template, like the fields of the parent object or the
+    /// resolved arguments (the ones omitted on the query, with a default value, are not present otherwise)
+    pub fn with_value(mut self, name: &'static str, value: impl Display) -> Self {
+        self.values.push((name, value.to_string()));
+        self
+    }
+
+    /// Builds the object to authorize
+    fn object(&self, ctx: &Context<'_>) -> Result<String> {
+        let args = ctx.field().arguments()?;
+        let object = fill_template(self.template, |name| {
+            if let Some((_, value)) = self.values.iter().find(|(n, _)| *n == name) {
+                return Ok(Some(value.clone()));
+            }
+            args.iter()
+                .find(|(n, _)| n.as_str() == name)
+                .and_then(|(_, v)| match v {
+                    Value::String(s) => Some(s.clone()),
+                    Value::Number(n) => Some(n.to_string()),
+                    Value::Boolean(b) => Some(b.to_string()),
+                    Value::Enum(e) => Some(e.to_string()),
+                    _ => None,
+                })
+                .map(|value| client_value(name, value))
+                .transpose()
+        })
+        .map_err(Box::<GraphQLError>::from)?;
+        Ok(object)
+    }
+}
+
+impl<S: Subject, St: AuthState<S>> Guard for AuthTemplateGuard<S, St> {
+    async fn check(&self, ctx: &Context<'_>) -> Result<()> {
+        authorize::<S, St>(ctx, self.relation, || self.object(ctx).map(Cow::Owned)).await
+    }
+}
+
+/// Authorizes the subject on the context for the relation of the object, built once authenticated
+async fn authorize<'a, S: Subject, St: AuthState<S>>(
+    ctx: &Context<'_>,
+    relation: &str,
+    object: impl FnOnce() -> Result<Cow<'a, str>>,
+) -> Result<()> {
+    let sub = ctx.data::<Option<S>>().map_err(Box::<GraphQLError>::from)?.as_ref();
+    match sub {
+        Some(sub) => {
+            let state = ctx.data::<St>().map_err(Box::<GraphQLError>::from)?;
+            let object = object()?;
+            let res = match ctx.data_opt::<AuthzCache>() {
+                Some(cache) => cache.authorize(state.authz(), sub, relation, &object).await,
+                None => state.authz().authorize(sub, relation, &object).await,
+            };
+            Ok(res.map_err(Box::<GraphQLError>::from)?)
+        }
+        None => Err(
+            GraphQLError::from_err(err!(AuthErrorCode::AuthMissing, "The subject must be authenticated")).into(),
+        ),
+    }
+}
+
+/// Scope [Guard].
+///
+/// This guard will use the `Option<S>` from the GraphQL context to verify the subject has the
+/// [required scopes](RequiredScopes), failing if it's not available. When the scopes are insufficient, the
+/// `WWW-Authenticate` header is included on the response.
+///
+/// ``` rust ignore
+/// #[graphql(guard = "ScopeGuard::<Subject>::all([\"documents:read\"])")]
+/// async fn documents(&self) -> Vec<Document> { .. }
+/// ```
+pub struct ScopeGuard<S: SubjectScopes> {
+    scopes: RequiredScopes,
+    // Needed for the compiler
+    sub_type: PhantomData<S>,
+}
+
+impl<S: SubjectScopes> ScopeGuard<S> {
+    /// Creates a new scope guard with the given required scopes
+    pub fn new(scopes: RequiredScopes) -> Self {
+        ScopeGuard {
+            scopes,
+            sub_type: PhantomData,
+        }
+    }
+
+    /// Creates a new scope guard requiring every one of the scopes
+    pub fn all(scopes: impl IntoIterator<Item = &'static str>) -> Self {
+        Self::new(RequiredScopes::all(scopes))
+    }
+
+    /// Creates a new scope guard requiring at least one of the scopes
+    pub fn any(scopes: impl IntoIterator<Item = &'static str>) -> Self {
+        Self::new(RequiredScopes::any(scopes))
+    }
+}
+
+impl<S: SubjectScopes> Guard for ScopeGuard<S> {
     async fn check(&self, ctx: &Context<'_>) -> Result<()> {
         let sub = ctx.data::<Option<S>>().map_err(Box::<GraphQLError>::from)?.as_ref();
         match sub {
-            Some(sub) => {
-                let state = ctx.data::<St>().map_err(Box::<GraphQLError>::from)?;
-                Ok(state.authz().authorize(sub, self.relation, self.object).await?)
-            }
+            Some(sub) => self.scopes.verify(sub).map_err(|err| {
+                ctx.append_http_header(WWW_AUTHENTICATE, self.scopes.www_authenticate());
+                Box::<GraphQLError>::from(err).into()
+            }),
             None => Err(
                 GraphQLError::from_err(err!(AuthErrorCode::AuthMissing, "The subject must be authenticated")).into(),
             ),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::{EmptyMutation, EmptySubscription, Object, Request, Schema, Variables};
+
+    use super::*;
+    use crate::auth::{
+        test_support::{TestAuthn, TestState, TestSubject},
+        InMemoryRelationStore, RebacAuthorizationService, RebacSchema,
+    };
+
+    type TestGuard = AuthGuard<TestSubject, TestState<RebacAuthorizationService<InMemoryRelationStore>>>;
+
+    struct Document {
+        owner: String,
+    }
+
+    #[Object]
+    impl Document {
+        #[graphql(guard = "TestGuard::with_template(\"viewer\", \"user:{owner}\").with_value(\"owner\", &self.owner)")]
+        async fn owner(&self) -> &str {
+            &self.owner
+        }
+    }
+
+    struct Query;
+
+    #[Object]
+    impl Query {
+        #[graphql(guard = "TestGuard::with_template(\"viewer\", \"doc:{id}\")")]
+        async fn document(&self, id: String) -> Document {
+            Document { owner: format!("owner{id}") }
+        }
+
+        #[graphql(guard = "TestGuard::with_template(\"viewer\", \"folder:{folder}\").with_value(\"folder\", &folder)")]
+        async fn folder(&self, #[graphql(default = "root")] folder: String) -> String {
+            folder
+        }
+    }
+
+    #[tokio::test]
+    async fn test_auth_guard() {
+        let rebac = RebacSchema::parse(
+            "type user {\n relation viewer: user\n}\ntype doc {\n relation viewer: user\n}\ntype folder {\n relation \
+             viewer: user\n}",
+        )
+        .unwrap();
+        let authz = RebacAuthorizationService::new(rebac, InMemoryRelationStore::new());
+        let tuples = ["doc:1#viewer@user:1", "doc:2#viewer@user:1", "user:owner1#viewer@user:1", "folder:root#viewer@user:1"];
+        authz.write(&tuples.map(|t| t.parse().unwrap())).await.unwrap();
+        let schema = Schema::build(Query, EmptyMutation, EmptySubscription)
+            .data(Some(TestSubject::new("user:1")))
+            .data(TestState(TestAuthn::default(), authz))
+            .finish();
+        let error_code = |res: async_graphql::Response| {
+            res.errors[0].extensions.as_ref().and_then(|e| e.get("errorCode")).cloned()
+        };
+
+        // Arguments and parent values
+        let res = schema.execute(r#"{ document(id: "1") { owner } }"#).await;
+        assert!(res.errors.is_empty(), "{:?}", res.errors);
+        let res = schema.execute(r#"{ document(id: "2") { owner } }"#).await;
+        assert_eq!(error_code(res), Some(Value::from("AUTH_FAILED")));
+        let res = schema.execute(r#"{ document(id: "3") { owner } }"#).await;
+        assert_eq!(error_code(res), Some(Value::from("AUTH_FAILED")));
+        let res = schema.execute(r#"{ document(id: "1#viewer@user:1") { owner } }"#).await;
+        assert_eq!(error_code(res), Some(Value::from("BAD_REQUEST")));
+
+        // Variables
+        let query = r#"query ($id: String!) { document(id: $id) { owner } }"#;
+        let res = schema
+            .execute(Request::new(query).variables(Variables::from_json(serde_json::json!({ "id": "1" }))))
+            .await;
+        assert!(res.errors.is_empty(), "{:?}", res.errors);
+
+        // Omitted arguments with a default value
+        let res = schema.execute("{ folder }").await;
+        assert!(res.errors.is_empty(), "{:?}", res.errors);
+        let res = schema.execute(r#"{ folder(folder: "other") }"#).await;
+        assert_eq!(error_code(res), Some(Value::from("AUTH_FAILED")));
+    }
+}
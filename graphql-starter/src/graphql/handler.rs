@@ -54,12 +54,12 @@ mod auth {
     use tracing::Instrument;
 
     use crate::{
-        auth::{Auth, AuthErrorCode, AuthState, AuthenticationService, Subject},
+        auth::{Auth, AuthCredentials, AuthState, AuthenticationService, AuthzCache, Subject},
         axum::{
             extract::{AcceptLanguage, Extension},
             CorsService, CorsState,
         },
-        error::{err, ApiError, GenericErrorCode, MapToErr},
+        error::{err, ApiError, ErrorDebug, GenericErrorCode, MapToErr, ReportedSubject},
         graphql::GraphQLBatchRequest,
         request_id::RequestId,
     };
@@ -76,8 +76,8 @@ mod auth {
 
     /// Handler for [batch requests](https://www.apollographql.com/blog/apollo-client/performance/batching-client-graphql-queries/).
     ///
-    /// [RequestId], [`Option<Subject>`](Subject) and [AcceptLanguage] will be added to the GraphQL context before
-    /// executing the request on the schema.
+    /// [RequestId], [`Option<Subject>`](Subject), [ReportedSubject], [AcceptLanguage] and the request [AuthzCache] will
+    /// be added to the GraphQL context before executing the request on the schema.
     ///
     /// This handler expects two extensions:
     /// - `Schema<Query, Mutation, Subscription>` with the GraphQL [Schema]
@@ -85,12 +85,16 @@ mod auth {
     ///
     /// And optionally:
     /// - `RequestDataMiddleware<Subject>` with the [RequestDataMiddleware]
+    /// - `ErrorDebug` to enable the [debug mode](crate::error::ErrorDebug) for the request errors
+    #[allow(clippy::too_many_arguments)]
     pub async fn graphql_batch_handler<S: Subject, M: RequestDataMiddleware<S>, Query, Mutation, Subscription>(
         Extension(schema): Extension<Schema<Query, Mutation, Subscription>>,
         Extension(request_id): Extension<RequestId>,
         middleware: Option<Extension<M>>,
+        error_debug: Option<Extension<ErrorDebug>>,
         subject: Option<Auth<S>>,
         accept_language: AcceptLanguage,
+        authz_cache: AuthzCache,
         req: GraphQLBatchRequest,
     ) -> GraphQLResponse
     where
@@ -122,8 +126,17 @@ mod auth {
                 }
             }
         }
-        // Include the request_id, subject and accept language into the GraphQL context
-        req = req.data(request_id).data(subject).data(accept_language);
+        // Include the request_id, subject, accept language and authorization cache into the GraphQL context
+        let reported_subject = ReportedSubject::new(subject.as_ref().map(|s| s.to_string()));
+        req = req
+            .data(request_id)
+            .data(subject)
+            .data(reported_subject)
+            .data(accept_language)
+            .data(authz_cache);
+        if let Some(Extension(error_debug)) = error_debug {
+            req = req.data(error_debug);
+        }
         // Execute the requests, instrumenting them with the operation name (if present)
         let mut res = match req {
             BatchRequest::Single(request) => {
@@ -164,8 +177,8 @@ mod auth {
     /// **Note**: For HTTP/1.1 requests, this handler requires the request method to be `GET`; in later versions,
     /// `CONNECT` is used instead. To support both, it should be used with [`any`](axum::routing::any).
     ///
-    /// [RequestId], [`Option<Subject>`](Subject) and [AcceptLanguage] will be added to the GraphQL context before
-    /// executing the request on the schema.
+    /// [RequestId], [`Option<Subject>`](Subject), [ReportedSubject] and [AcceptLanguage] will be added to the GraphQL
+    /// context before executing the request on the schema.
     ///
     /// This handler expects two extensions:
     /// - `Schema<Query, Mutation, Subscription>` with the GraphQL [Schema]
@@ -173,6 +186,7 @@ mod auth {
     ///
     /// And optionally:
     /// - `RequestDataMiddleware<Subject>` with the [RequestDataMiddleware]
+    /// - `ErrorDebug` to enable the [debug mode](crate::error::ErrorDebug) for the subscription errors
     ///
     /// Authentication will be performed using the same criteria than [Auth](crate::auth::Auth) extractor,
     /// retrieving the Cookie from the `GET` request and the token from the
@@ -199,6 +213,7 @@ mod auth {
         Subscription: SubscriptionType + 'static,
     {
         let (mut parts, _body) = req.into_parts();
+        let error_debug = parts.extensions.get::<ErrorDebug>().copied();
 
         // Retrieve `Origin` header set by browsers
         let origin_header = match parts
@@ -214,38 +229,23 @@ mod auth {
             Err(err) => return ApiError::from_err(err).into_response(),
         };
         // If it's present, check it's allowed
-        if let Some(origin_header) = origin_header {
-            if !state.cors().allowed_origins().iter().any(|o| o == origin_header) {
-                return ApiError::from_err(err!(GenericErrorCode::Forbidden, "The origin is not allowed"))
-                    .into_response();
-            }
+        if let Some(origin_header) = origin_header
+            && !state.cors().allowed_origins().iter().any(|o| o == origin_header)
+        {
+            return ApiError::from_err(err!(GenericErrorCode::Forbidden, "The origin is not allowed")).into_response();
         }
 
-        // Retrieve token & cookie names
+        // Retrieve the auth header names & cookies
         let authn = state.authn().clone();
-        let auth_header_name = authn.header_name().to_lowercase();
-        let auth_cookie_name = authn.cookie_name().to_owned();
-
-        // Retrieve the auth cookie value
-        let cookies = match parts
-            .headers
-            .get(http::header::COOKIE)
-            .map(|v| {
-                v.to_str()
-                    .map_to_err_with(AuthErrorCode::AuthMalformedCookies, "Couldn't parse request cookies")
-            })
-            .transpose()
-        {
+        let auth_header_names = authn
+            .header_names()
+            .into_iter()
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>();
+        let credentials = match AuthCredentials::from_headers(&parts.headers, [], authn.cookie_names()) {
             Ok(c) => c,
             Err(err) => return ApiError::from_err(err).into_response(),
         };
-        let auth_cookie_value = cookies
-            .and_then(|cookies| {
-                cookies
-                    .split("; ")
-                    .find_map(|cookie| cookie.strip_prefix(&format!("{auth_cookie_name}=")))
-            })
-            .map(|s| s.to_owned());
 
         // Based on https://github.com/async-graphql/async-graphql/blob/master/integrations/axum/src/subscription.rs
         // Extract GraphQL WebSocket protocol
@@ -269,16 +269,21 @@ mod auth {
                         // Authenticate the subject on connection init
                         async move {
                             let mut data = Data::default();
-                            // Retrieve auth token from the payload
-                            let auth_token = payload.as_object().and_then(|payload| {
-                                payload
-                                    .iter()
-                                    .find(|(k, _)| k.to_lowercase() == auth_header_name)
-                                    .and_then(|(_, v)| v.as_str())
-                            });
+                            // Retrieve auth tokens from the payload
+                            let mut credentials = credentials;
+                            if let Some(payload) = payload.as_object() {
+                                for (key, value) in payload {
+                                    if let Some(value) = value.as_str()
+                                        && auth_header_names.contains(&key.to_lowercase())
+                                    {
+                                        credentials = credentials.with_token(key, value);
+                                    }
+                                }
+                            }
                             // Authenticate the subject
-                            let subject = authn.authenticate(auth_token, auth_cookie_value.as_deref()).await?;
+                            let subject = authn.authenticate_credentials(&credentials).await?;
                             tracing::trace!("Authenticated as {subject}");
+                            let reported_subject = ReportedSubject::new(Some(subject.to_string()));
                             let subject = Some(subject);
 
                             // Call the request data middleware to include additional data
@@ -289,7 +294,11 @@ mod auth {
                             // Include the request_id, subject and accept language into the GraphQL context
                             data.insert(request_id);
                             data.insert(subject);
+                            data.insert(reported_subject);
                             data.insert(accept_language);
+                            if let Some(error_debug) = error_debug {
+                                data.insert(error_debug);
+                            }
 
                             Ok(data)
                         }
@@ -7,7 +7,14 @@ crate::using! {
     pub sdl,
     pub handler,
     pub extract,
+    pub error_extension,
 }
 
 #[cfg(feature = "auth")]
 crate::using! { pub guard }
+
+#[cfg(feature = "error-info-summary")]
+crate::using! { pub error_catalog }
+
+#[cfg(feature = "garde")]
+mod validated;
@@ -0,0 +1,60 @@
+use std::borrow::Cow;
+
+use async_graphql::{registry, InputType, InputValueError, InputValueResult, Value};
+use error_info::ErrorInfo;
+
+use crate::{
+    axum::extract::Validated,
+    error::{validation_errors, ValidationErrorCode, VALIDATION_ERRORS},
+};
+
+/// GraphQL inputs wrapped in [Validated] are validated with [garde] when parsed, failing with the messages of every
+/// invalid field on the `validationErrors` extension.
+impl<T> InputType for Validated<T>
+where
+    T: InputType + garde::Validate,
+    T::Context: Default,
+{
+    type RawValueType = T::RawValueType;
+
+    fn type_name() -> Cow<'static, str> {
+        T::type_name()
+    }
+
+    fn qualified_type_name() -> String {
+        T::qualified_type_name()
+    }
+
+    fn create_type_info(registry: &mut registry::Registry) -> String {
+        T::create_type_info(registry)
+    }
+
+    fn parse(value: Option<Value>) -> InputValueResult<Self> {
+        let value = T::parse(value).map_err(InputValueError::propagate)?;
+        if let Err(report) = value.validate() {
+            tracing::info!("Invalid GraphQL input: {report}");
+            let code = ValidationErrorCode::ValidationFailed;
+            let status = code.status();
+            let mut err = InputValueError::custom(code.message())
+                .with_extension("statusCode", status.as_u16())
+                .with_extension("errorCode", code.code())
+                .with_extension("rawMessage", code.raw_message());
+            if let Some(reason) = status.canonical_reason() {
+                err = err.with_extension("statusKind", reason);
+            }
+            if let Ok(errors) = Value::from_json(validation_errors(&report)) {
+                err = err.with_extension(VALIDATION_ERRORS, errors);
+            }
+            return Err(err);
+        }
+        Ok(Validated(value))
+    }
+
+    fn to_value(&self) -> Value {
+        self.0.to_value()
+    }
+
+    fn as_raw_value(&self) -> Option<&Self::RawValueType> {
+        self.0.as_raw_value()
+    }
+}
@@ -34,6 +34,13 @@ pub mod macros;
 
 /// Re-exported crates
 pub mod crates {
+    pub mod error_info {
+        pub use ::error_info::*;
+    }
+    #[cfg(feature = "graphql")]
+    pub mod async_graphql {
+        pub use ::async_graphql::*;
+    }
     #[cfg(feature = "paste")]
     pub mod paste {
         pub use ::paste::*;
@@ -23,7 +23,7 @@ impl<T> MaybeOption<T> {
     /// # Examples
     ///
     /// ```
-    /// # use graphql_starter::utils::MaybeOption;
+    /// # use graphql_starter::MaybeOption;
     /// let x: MaybeOption<u32> = MaybeOption::Unset;
     /// assert_eq!(x.is_unset(), true);
     ///
@@ -40,7 +40,7 @@ impl<T> MaybeOption<T> {
     /// # Examples
     ///
     /// ```
-    /// # use graphql_starter::utils::MaybeOption;
+    /// # use graphql_starter::MaybeOption;
     /// let x: MaybeOption<u32> = MaybeOption::Some(2);
     /// assert_eq!(x.is_none(), false);
     ///
@@ -57,7 +57,7 @@ impl<T> MaybeOption<T> {
     /// # Examples
     ///
     /// ```
-    /// # use graphql_starter::utils::MaybeOption;
+    /// # use graphql_starter::MaybeOption;
     /// let x: MaybeOption<u32> = MaybeOption::Some(2);
     /// assert_eq!(x.is_some(), true);
     ///
@@ -81,7 +81,7 @@ impl<T> MaybeOption<T> {
     /// [`map`]: MaybeOption::map
     ///
     /// ```
-    /// # use graphql_starter::utils::MaybeOption;
+    /// # use graphql_starter::MaybeOption;
     /// let text: MaybeOption<String> = MaybeOption::Some("Hello, world!".to_string());
     /// // First, cast `Option<String>` to `Option<&String>` with `as_ref`,
     /// // then consume *that* with `map`, leaving `text` on the stack.
@@ -102,7 +102,7 @@ impl<T> MaybeOption<T> {
     /// # Examples
     ///
     /// ```
-    /// # use graphql_starter::utils::MaybeOption;
+    /// # use graphql_starter::MaybeOption;
     /// let mut x = MaybeOption::Some(2);
     /// match x.as_mut() {
     ///     MaybeOption::Some(v) => *v = 42,
@@ -128,7 +128,7 @@ impl<T> MaybeOption<T> {
     /// <code>MaybeOption<[usize]></code>, consuming the original:
     ///
     /// ```
-    /// # use graphql_starter::utils::MaybeOption;
+    /// # use graphql_starter::MaybeOption;
     /// let maybe_some_string = MaybeOption::Some(String::from("Hello, World!"));
     /// // `Option::map` takes self *by value*, consuming `maybe_some_string`
     /// let maybe_some_len = maybe_some_string.map(|s| s.len());
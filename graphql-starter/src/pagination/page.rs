@@ -58,13 +58,14 @@ impl PageQuery {
         max_page_size: Option<usize>,
     ) -> Result<Self> {
         // Set defaults if set
-        if let Some(default_limit) = default_limit {
-            if first.is_none() && last.is_none() {
-                if before.is_some() {
-                    last = Some(default_limit);
-                } else {
-                    first = Some(default_limit);
-                }
+        if let Some(default_limit) = default_limit
+            && first.is_none()
+            && last.is_none()
+        {
+            if before.is_some() {
+                last = Some(default_limit);
+            } else {
+                first = Some(default_limit);
             }
         }
         // Check wether first and last values are valid
@@ -87,10 +88,10 @@ impl PageQuery {
 
         if let Some(first) = first {
             // Validate maximum, if set
-            if let Some(max) = max_page_size {
-                if first > max {
-                    return Err(err!(PaginationErrorCode::PageExceedsLimit { field: "first", max }));
-                }
+            if let Some(max) = max_page_size
+                && first > max
+            {
+                return Err(err!(PaginationErrorCode::PageExceedsLimit { field: "first", max }));
             }
             // Forward paginating
             if before.is_some() {
@@ -100,10 +101,10 @@ impl PageQuery {
             }
         } else if let Some(last) = last {
             // Validate maximum, if set
-            if let Some(max) = max_page_size {
-                if last > max {
-                    return Err(err!(PaginationErrorCode::PageExceedsLimit { field: "last", max }));
-                }
+            if let Some(max) = max_page_size
+                && last > max
+            {
+                return Err(err!(PaginationErrorCode::PageExceedsLimit { field: "last", max }));
             }
             // Backward paginating
             if after.is_some() {
@@ -362,6 +363,35 @@ impl<T> Page<T> {
         // 4. Return edges
         Ok(Self::new(start > 0, end < items_len, total_items, edges))
     }
+
+    /// Keeps only the edges whose node the _subject_ is allowed to perform the _relation_ on, authorizing all of them at
+    /// once with [authorize_many](crate::auth::AuthorizationService::authorize_many).
+    ///
+    /// The closure must return the object to authorize for each node.
+    ///
+    /// When any edge is filtered out, the total items are no longer known and are set to [None].
+    #[cfg(feature = "auth")]
+    pub async fn filter_authorized<S, A, F>(self, authz: &A, subject: &S, relation: &str, object: F) -> Result<Self>
+    where
+        S: crate::auth::Subject,
+        A: crate::auth::AuthorizationService<S>,
+        F: Fn(&T) -> String,
+    {
+        let objects = self.edges.iter().map(|e| object(&e.node)).collect::<Vec<_>>();
+        let objects = objects.iter().map(String::as_str).collect::<Vec<_>>();
+        let allowed = authz.authorize_many(subject, relation, &objects).await?;
+        let total_items = self.total_items.filter(|_| allowed.iter().all(|allowed| *allowed));
+        Ok(Self::from_iter(
+            self.page_info.has_previous_page,
+            self.page_info.has_next_page,
+            total_items,
+            self.edges
+                .into_iter()
+                .zip(allowed)
+                .filter(|(_, allowed)| *allowed)
+                .map(|(edge, _)| edge),
+        ))
+    }
 }
 
 // Based on https://stackoverflow.com/a/65004188
@@ -464,4 +494,35 @@ mod tests {
             vec![3, 4, 5, 6, 7, 8]
         );
     }
+
+    #[cfg(feature = "auth")]
+    #[tokio::test]
+    async fn test_filter_authorized() {
+        use crate::auth::{test_support::TestSubject, InMemoryRelationStore, RebacAuthorizationService, RebacSchema};
+
+        let subject = TestSubject::new("user:1");
+
+        let schema = RebacSchema::parse("type user {}\ntype doc {\n relation viewer: user\n}").unwrap();
+        let authz = RebacAuthorizationService::new(schema, InMemoryRelationStore::new());
+        let tuples = ["doc:1#viewer@user:1".parse().unwrap(), "doc:4#viewer@user:1".parse().unwrap()];
+        authz.write(&tuples).await.unwrap();
+
+        let page = Page::from_items((0..5).collect(), PageQuery::decode(Some(5), None, None, None, None, None).unwrap())
+            .unwrap()
+            .filter_authorized(&authz, &subject, "viewer", |n| format!("doc:{n}"))
+            .await
+            .unwrap();
+
+        assert_eq!(page.total_items, None);
+        assert_eq!(page.page_info.start_cursor, Some(OpaqueCursor::new(&1).unwrap()));
+        assert_eq!(page.into_iter().map(|e| e.node).collect::<Vec<_>>(), vec![1, 4]);
+
+        // The total items are kept when every edge is allowed
+        let page = Page::from_items(vec![1, 4], PageQuery::decode(Some(5), None, None, None, None, None).unwrap())
+            .unwrap()
+            .filter_authorized(&authz, &subject, "viewer", |n| format!("doc:{n}"))
+            .await
+            .unwrap();
+        assert_eq!(page.total_items, Some(2));
+    }
 }
@@ -2,6 +2,7 @@
 
 use std::{
     fmt,
+    str::FromStr,
     task::{Context, Poll},
 };
 
@@ -10,6 +11,9 @@ use tower::{Layer, Service};
 use ulid::Ulid;
 use uuid::Uuid;
 
+/// Name of the header used to propagate the [RequestId] between services
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
 /// A new type around [`ulid::Ulid`]
 #[derive(Clone, Copy, Debug)]
 pub struct RequestId(Ulid);
@@ -38,6 +42,14 @@ impl AsRef<Ulid> for &RequestId {
     }
 }
 
+impl FromStr for RequestId {
+    type Err = ulid::DecodeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ulid::from_string(s).map(Self)
+    }
+}
+
 impl fmt::Display for RequestId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         let mut buffer = [0; ulid::ULID_LEN];
@@ -49,11 +61,12 @@ impl fmt::Display for RequestId {
 #[derive(Clone, Debug)]
 pub struct RequestIdService<S> {
     inner: S,
+    trust_header: bool,
 }
 
 impl<S> RequestIdService<S> {
-    fn new(inner: S) -> Self {
-        Self { inner }
+    fn new(inner: S, trust_header: bool) -> Self {
+        Self { inner, trust_header }
     }
 }
 
@@ -71,13 +84,23 @@ where
     }
 
     fn call(&mut self, mut req: Request<B>) -> Self::Future {
-        let id = RequestId::new();
+        // Reuse the id propagated by upstream services (if any and trusted)
+        let id = req
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .filter(|_| self.trust_header)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok())
+            .unwrap_or_else(RequestId::new);
         req.extensions_mut().insert(id);
         self.inner.call(req)
     }
 }
 
 /// Layer to apply [`RequestIdService`] middleware.
+///
+/// A new [RequestId] is generated for every request, see [TrustedRequestIdLayer] to reuse the one of the upstream
+/// services.
 #[derive(Clone, Debug)]
 pub struct RequestIdLayer;
 
@@ -85,6 +108,50 @@ impl<S> Layer<S> for RequestIdLayer {
     type Service = RequestIdService<S>;
 
     fn layer(&self, inner: S) -> Self::Service {
-        RequestIdService::new(inner)
+        RequestIdService::new(inner, false)
+    }
+}
+
+/// Layer to apply [`RequestIdService`] middleware, reusing the valid [REQUEST_ID_HEADER] of the requests like the
+/// ones sent by [ApiErrorClientLayer](crate::error::ApiErrorClientLayer).
+///
+/// The header can be set by any client, so this should only be used for services not exposed to untrusted clients.
+#[derive(Clone, Debug)]
+pub struct TrustedRequestIdLayer;
+
+impl<S> Layer<S> for TrustedRequestIdLayer {
+    type Service = RequestIdService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestIdService::new(inner, true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use tower::{service_fn, ServiceExt};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_request_id_layer() {
+        let upstream_id = "01ARZ3NDEKTSV4RRFFQ69G5FAV";
+        let req = || {
+            Request::builder()
+                .header(REQUEST_ID_HEADER, upstream_id)
+                .body(())
+                .unwrap()
+        };
+        let svc = service_fn(|req: Request<()>| async move {
+            Ok::<_, Infallible>(req.extensions().get::<RequestId>().unwrap().to_string())
+        });
+
+        let id = RequestIdLayer.layer(svc).oneshot(req()).await.unwrap();
+        assert_ne!(id, upstream_id);
+
+        let id = TrustedRequestIdLayer.layer(svc).oneshot(req()).await.unwrap();
+        assert_eq!(id, upstream_id);
     }
 }
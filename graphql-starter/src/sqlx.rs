@@ -1,5 +1,34 @@
 //! Utilities to work with [sqlx]
 
+#[cfg(feature = "postgres")]
+crate::using!(pub classifier);
+
+/// Maps the errors of [sqlx_query_paginated_as](crate::sqlx_query_paginated_as), classifying them when the `postgres`
+/// feature is enabled
+#[doc(hidden)]
+pub trait MapPaginatedErr<T> {
+    /// Maps the error of the paginated query
+    fn map_paginated_err(self) -> crate::error::Result<T>;
+}
+
+#[cfg(feature = "postgres")]
+impl<T> MapPaginatedErr<T> for Result<T, ::sqlx::Error> {
+    #[track_caller]
+    fn map_paginated_err(self) -> crate::error::Result<T> {
+        self.map_sqlx_err("Error fetching paginated query")
+    }
+}
+
+#[cfg(not(feature = "postgres"))]
+impl<T, E: std::fmt::Display + Send + Sync + 'static> MapPaginatedErr<T> for Result<T, E> {
+    #[track_caller]
+    fn map_paginated_err(self) -> crate::error::Result<T> {
+        use crate::error::{GenericErrorCode, MapToErr};
+
+        self.map_to_err_with(GenericErrorCode::InternalServerError, "Error fetching paginated query")
+    }
+}
+
 /// Similar to `sqlx::query_as!` but with pagination capabilities.
 ///
 /// **Note**: this macro won't populate `total_items` in the resulting page, it must be queried afterwards if needed.
@@ -49,8 +78,8 @@ macro_rules! sqlx_query_paginated_as {
         args = [$($args:expr),*]
     ) => ({
         use $crate::{
-            error::{GenericErrorCode, MapToErr},
             pagination::{IntoCursorVec, Page, PageQuery},
+            sqlx::MapPaginatedErr,
         };
         let limit;
         let backward;
@@ -116,10 +145,7 @@ macro_rules! sqlx_query_paginated_as {
                 }
             }
         }
-        .map_to_err_with(
-            GenericErrorCode::InternalServerError,
-            "Error fetching paginated query",
-        )?;
+        .map_paginated_err()?;
 
         let mut has_previous_page = false;
         let mut has_next_page = false;
@@ -0,0 +1,206 @@
+use std::{collections::HashMap, fmt, panic::Location, sync::Arc};
+
+use error_info::ErrorInfo;
+
+use crate::error::{Error, GenericErrorCode, Result};
+
+type ConstraintError = Arc<dyn Fn() -> Box<Error> + Send + Sync>;
+
+/// Classifier of [sqlx::Error], with custom errors for some constraints on top of the default classification of
+/// [classify_sqlx_error].
+///
+/// It's meant to be built once and kept on the app state:
+///
+/// ``` rust ignore
+/// let classifier = SqlxErrorClassifier::new()
+///     .with_constraint("users_email_key", UserErrorCode::EmailAlreadyExists);
+///
+/// sqlx::query("INSERT INTO users ...")
+///     .execute(&pool)
+///     .await
+///     .map_sqlx_err_with(&classifier, "Couldn't insert the user")?;
+/// ```
+#[derive(Clone, Default)]
+pub struct SqlxErrorClassifier {
+    constraints: HashMap<String, ConstraintError>,
+}
+
+impl fmt::Debug for SqlxErrorClassifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SqlxErrorClassifier")
+            .field("constraints", &self.constraints.keys())
+            .finish()
+    }
+}
+
+impl SqlxErrorClassifier {
+    /// Creates a new classifier without custom errors
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Includes the error to be returned when the given constraint is violated, overriding the default classification
+    pub fn with_constraint(
+        mut self,
+        constraint: impl Into<String>,
+        info: impl ErrorInfo + Clone + Send + Sync + 'static,
+    ) -> Self {
+        self.constraints
+            .insert(constraint.into(), Arc::new(move || Error::new(info.clone())));
+        self
+    }
+
+    /// Classifies the [sqlx::Error], using the custom error of the violated constraint if any
+    #[track_caller]
+    pub fn classify(&self, err: ::sqlx::Error) -> Box<Error> {
+        let location = Location::caller();
+        let constraint_err = match &err {
+            ::sqlx::Error::Database(db_err) => db_err.constraint().and_then(|c| self.constraints.get(c)),
+            _ => None,
+        };
+        match constraint_err {
+            Some(constraint_err) => constraint_err().with_error_source(err).located(location),
+            None => classify_sqlx_error(err).located(location),
+        }
+    }
+}
+
+/// Classifies a [sqlx::Error] into a [`Box<Error>`](Error) with a semantic error code:
+/// - `RowNotFound` is mapped to [NotFound](GenericErrorCode::NotFound)
+/// - Unique (`23505`) and foreign key (`23503`) violations are mapped to [Conflict](GenericErrorCode::Conflict)
+/// - Check (`23514`) and not-null (`23502`) violations or data exceptions (`22XXX`) are mapped to
+///   [BadRequest](GenericErrorCode::BadRequest)
+/// - Serialization failures (`40001`), deadlocks (`40P01`), unavailable connections and pool timeouts are mapped to
+///   [ServiceUnavailable](GenericErrorCode::ServiceUnavailable), which is retryable
+/// - Any other error is mapped to [InternalServerError](GenericErrorCode::InternalServerError)
+///
+/// Custom errors for specific constraints can be provided with a [SqlxErrorClassifier].
+#[track_caller]
+pub fn classify_sqlx_error(err: ::sqlx::Error) -> Box<Error> {
+    let location = Location::caller();
+    let ret = match &err {
+        ::sqlx::Error::RowNotFound => Error::new(GenericErrorCode::NotFound),
+        ::sqlx::Error::PoolTimedOut | ::sqlx::Error::PoolClosed | ::sqlx::Error::Io(_) => {
+            Error::new(GenericErrorCode::ServiceUnavailable)
+        }
+        ::sqlx::Error::Database(db_err) => match db_err.code().as_deref() {
+            Some("23505" | "23503") => Error::new(GenericErrorCode::Conflict),
+            Some("23514" | "23502") => Error::new(GenericErrorCode::BadRequest),
+            Some(code) if code.starts_with("22") => Error::new(GenericErrorCode::BadRequest),
+            Some("40001" | "40P01" | "53300" | "57P01" | "57P03") => Error::new(GenericErrorCode::ServiceUnavailable),
+            _ => Error::new(GenericErrorCode::InternalServerError),
+        },
+        _ => Error::new(GenericErrorCode::InternalServerError),
+    };
+    ret.with_error_source(err).located(location)
+}
+
+/// Classifies the [sqlx::Error] with [classify_sqlx_error]
+impl From<::sqlx::Error> for Box<Error> {
+    #[track_caller]
+    fn from(err: ::sqlx::Error) -> Self {
+        classify_sqlx_error(err)
+    }
+}
+
+/// Utility trait to map a [sqlx] [`Result<T, sqlx::Error>`](::sqlx::Result) to a [`Result<T, Box<Error>>`]
+pub trait MapSqlxErr<T> {
+    /// Maps the error with [classify_sqlx_error], including the given reason
+    fn map_sqlx_err(self, reason: &'static str) -> Result<T>;
+    /// Maps the error with the given [SqlxErrorClassifier], including the given reason
+    fn map_sqlx_err_with(self, classifier: &SqlxErrorClassifier, reason: &'static str) -> Result<T>;
+}
+impl<T> MapSqlxErr<T> for Result<T, ::sqlx::Error> {
+    #[track_caller]
+    fn map_sqlx_err(self, reason: &'static str) -> Result<T> {
+        let location = Location::caller();
+        self.map_err(|err| classify_sqlx_error(err).with_reason(reason).located(location))
+    }
+
+    #[track_caller]
+    fn map_sqlx_err_with(self, classifier: &SqlxErrorClassifier, reason: &'static str) -> Result<T> {
+        let location = Location::caller();
+        self.map_err(|err| classifier.classify(err).with_reason(reason).located(location))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{borrow::Cow, error::Error as StdError, fmt};
+
+    use ::sqlx::error::{DatabaseError, ErrorKind};
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct PgError {
+        code: &'static str,
+        constraint: Option<&'static str>,
+    }
+    impl fmt::Display for PgError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "database error {}", self.code)
+        }
+    }
+    impl StdError for PgError {}
+    impl DatabaseError for PgError {
+        fn message(&self) -> &str {
+            "database error"
+        }
+
+        fn code(&self) -> Option<Cow<'_, str>> {
+            Some(self.code.into())
+        }
+
+        fn as_error(&self) -> &(dyn StdError + Send + Sync + 'static) {
+            self
+        }
+
+        fn as_error_mut(&mut self) -> &mut (dyn StdError + Send + Sync + 'static) {
+            self
+        }
+
+        fn into_error(self: Box<Self>) -> Box<dyn StdError + Send + Sync + 'static> {
+            self
+        }
+
+        fn constraint(&self) -> Option<&str> {
+            self.constraint
+        }
+
+        fn kind(&self) -> ErrorKind {
+            ErrorKind::Other
+        }
+    }
+
+    fn db_err(code: &'static str, constraint: Option<&'static str>) -> ::sqlx::Error {
+        ::sqlx::Error::Database(Box::new(PgError { code, constraint }))
+    }
+
+    #[test]
+    fn test_classify_sqlx_error() {
+        let code = |err: ::sqlx::Error| classify_sqlx_error(err).info().code();
+        assert_eq!(code(::sqlx::Error::RowNotFound), "NOT_FOUND");
+        assert_eq!(code(::sqlx::Error::PoolTimedOut), "SERVICE_UNAVAILABLE");
+        assert_eq!(code(db_err("23505", Some("users_pkey"))), "CONFLICT");
+        assert_eq!(code(db_err("23514", Some("users_age_check"))), "BAD_REQUEST");
+        assert_eq!(code(db_err("22001", None)), "BAD_REQUEST");
+        assert_eq!(code(db_err("42P01", None)), "INTERNAL_SERVER_ERROR");
+
+        let err = classify_sqlx_error(db_err("40001", None));
+        assert_eq!(err.info().code(), "SERVICE_UNAVAILABLE");
+        assert!(err.retry().is_some());
+
+        let classifier = SqlxErrorClassifier::new().with_constraint("users_email_key", GenericErrorCode::Forbidden);
+        let err: Result<()> =
+            Err(db_err("23505", Some("users_email_key"))).map_sqlx_err_with(&classifier, "Couldn't insert user");
+        let err = err.unwrap_err();
+        assert_eq!(err.info().code(), "FORBIDDEN");
+        assert_eq!(err.reason(), Some("Couldn't insert user"));
+        assert!(err.location().file().ends_with("classifier.rs"));
+        assert_eq!(
+            classifier.classify(db_err("23505", Some("users_pkey"))).info().code(),
+            "CONFLICT"
+        );
+    }
+}
@@ -121,7 +121,7 @@ where
         let this = self.project();
 
         if this.sleep.poll(cx).is_ready() {
-            let err = ApiError::from_err(Error::new(*this.response));
+            let err = ApiError::from_err(Error::new(*this.response).retryable());
             return Poll::Ready(Ok(err.into_response()));
         }
 
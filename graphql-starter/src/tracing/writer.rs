@@ -91,10 +91,11 @@ impl<W: io::Write> WriterInterceptor<'_, W> {
             lines.push_back(line.to_owned());
         }
         // If stream capabilities are enabled, send the log
-        if let Some(tx) = &mut self.stream_tx {
-            if tx.receiver_count() > 0 && tx.send(line.to_owned()).is_err() {
-                eprintln!("Couldn't send a log event to the stream")
-            }
+        if let Some(tx) = &mut self.stream_tx
+            && tx.receiver_count() > 0
+            && tx.send(line.to_owned()).is_err()
+        {
+            eprintln!("Couldn't send a log event to the stream")
         }
     }
 }
@@ -0,0 +1,124 @@
+use async_graphql::{EmptySubscription, Object, Schema, SimpleObject};
+use error_info::ErrorInfo;
+use graphql_starter as starter;
+use graphql_starter::{
+    err,
+    error::{GenericErrorCode, GraphQLResult, Result},
+    GraphQLErrors,
+};
+use http::StatusCode;
+
+#[derive(Clone, ErrorInfo, GraphQLErrors)]
+#[graphql_errors(result(name = CreateTodoResult, payload = Todo))]
+enum TodoErrorCode {
+    #[error(status = StatusCode::CONFLICT, message = "The todo \"{item}\" already exists")]
+    TodoAlreadyExists { item: String },
+    #[error(status = StatusCode::BAD_REQUEST, message = "The todo can't be empty")]
+    TodoEmpty,
+}
+
+#[derive(Clone, ErrorInfo, GraphQLErrors)]
+#[graphql_errors(crate = starter, result(name = LimitTodoResult, payload = Todo))]
+enum LimitErrorCode {
+    #[error(status = StatusCode::BAD_REQUEST, message = "The todo exceeds the limit of {max}")]
+    TodoLimitExceeded { max: i32 },
+}
+
+#[derive(SimpleObject)]
+struct Todo {
+    item: String,
+}
+
+async fn create_todo(item: String) -> Result<Todo> {
+    match item.as_str() {
+        "" => Err(err!(TodoErrorCode::TodoEmpty)),
+        "fail" => Err(err!(GenericErrorCode::Forbidden)),
+        "existing" => Err(err!(TodoErrorCode::TodoAlreadyExists { item })),
+        _ => Ok(Todo { item }),
+    }
+}
+
+async fn create_limited_todo(item: String) -> Result<Todo> {
+    if item.len() > 5 {
+        Err(err!(LimitErrorCode::TodoLimitExceeded { max: 5 }))
+    } else {
+        Ok(Todo { item })
+    }
+}
+
+struct Query;
+#[Object]
+impl Query {
+    async fn version(&self) -> &str {
+        "1"
+    }
+}
+
+struct Mutation;
+#[Object]
+impl Mutation {
+    async fn create_todo(&self, item: String) -> GraphQLResult<CreateTodoResult> {
+        Ok(CreateTodoResult::from_result(create_todo(item).await)?)
+    }
+
+    async fn create_limited_todo(&self, item: String) -> GraphQLResult<LimitTodoResult> {
+        Ok(LimitTodoResult::from_result(create_limited_todo(item).await)?)
+    }
+}
+
+#[tokio::test]
+async fn test_graphql_errors() {
+    let schema = Schema::new(Query, Mutation, EmptySubscription);
+    let query = |item: &str| {
+        format!(
+            r#"mutation {{
+                createTodo(item: "{item}") {{
+                    __typename
+                    ... on Todo {{ item }}
+                    ... on TodoAlreadyExistsError {{ message item }}
+                    ... on TodoEmptyError {{ message }}
+                }}
+            }}"#
+        )
+    };
+
+    let res = schema.execute(query("new")).await.into_result().unwrap();
+    assert_eq!(
+        res.data.into_json().unwrap()["createTodo"],
+        serde_json::json!({ "__typename": "Todo", "item": "new" })
+    );
+
+    let res = schema.execute(query("existing")).await.into_result().unwrap();
+    assert_eq!(
+        res.data.into_json().unwrap()["createTodo"],
+        serde_json::json!({
+            "__typename": "TodoAlreadyExistsError",
+            "message": "The todo \"existing\" already exists",
+            "item": "existing"
+        })
+    );
+
+    let res = schema.execute(query("")).await.into_result().unwrap();
+    assert_eq!(
+        res.data.into_json().unwrap()["createTodo"]["__typename"],
+        "TodoEmptyError"
+    );
+
+    let errors = schema.execute(query("fail")).await.into_result().unwrap_err();
+    assert_eq!(
+        errors[0].extensions.as_ref().unwrap().get("errorCode"),
+        Some(&async_graphql::Value::from("FORBIDDEN"))
+    );
+
+    let res = schema
+        .execute(
+            r#"mutation { createLimitedTodo(item: "too long") { ... on TodoLimitExceededError { message max } } }"#,
+        )
+        .await
+        .into_result()
+        .unwrap();
+    assert_eq!(
+        res.data.into_json().unwrap()["createLimitedTodo"],
+        serde_json::json!({ "message": "The todo exceeds the limit of 5", "max": 5 })
+    );
+}
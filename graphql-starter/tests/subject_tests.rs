@@ -0,0 +1,48 @@
+use std::collections::BTreeSet;
+
+use graphql_starter::{
+    auth::{SubjectId, SubjectRoles, SubjectScopes, SubjectTenant},
+    Subject,
+};
+
+#[derive(Debug, Clone, Subject)]
+struct User {
+    #[subject(id)]
+    id: u64,
+    #[subject(tenant)]
+    organization: Option<String>,
+    #[subject(scopes)]
+    scopes: Vec<String>,
+    #[subject(roles)]
+    roles: BTreeSet<&'static str>,
+}
+
+#[derive(Debug, Clone, Subject)]
+#[subject(skip_display)]
+struct Service(#[subject(id)] String, #[subject(tenant)] &'static str);
+
+impl std::fmt::Display for Service {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "service:{}", self.0)
+    }
+}
+
+#[test]
+fn test_derive_subject() {
+    let user = User {
+        id: 1,
+        organization: None,
+        scopes: vec!["documents:read".into(), "documents:write".into()],
+        roles: BTreeSet::from(["admin", "editor"]),
+    };
+    assert_eq!(user.to_string(), "1");
+    assert_eq!(user.subject_id(), "1");
+    assert_eq!(user.tenant(), None);
+    assert_eq!(user.scopes(), ["documents:read", "documents:write"]);
+    assert_eq!(user.roles(), ["admin", "editor"]);
+
+    let service = Service("billing".into(), "acme");
+    assert_eq!(service.to_string(), "service:billing");
+    assert_eq!(service.subject_id(), "billing");
+    assert_eq!(service.tenant().as_deref(), Some("acme"));
+}